@@ -62,6 +62,19 @@ pub enum Expr {
         lines: Vec<Expr>,
     },
 
+    /// ATX heading of Markdown such as `# Morning`.
+    Heading {
+        level: usize,
+        title: String,
+        span: Range<usize>,
+    },
+
+    Suppression {
+        kind: SuppressionKind,
+        rules: Vec<String>,
+        span: Range<usize>,
+    },
+
     Error {
         reason: String,
         span: Range<usize>,
@@ -69,6 +82,16 @@ pub enum Expr {
     NonTargetLine,
 }
 
+/// Scope of a suppression comment such as `<!-- journalint-disable-next-line time-jumped -->`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuppressionKind {
+    /// `journalint-disable-next-line` which suppresses violations on the next line.
+    NextLine,
+
+    /// `journalint-disable` which suppresses violations in the whole file.
+    File,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LooseTime(String);
 
@@ -182,6 +205,26 @@ pub trait Visitor<E> {
         Ok(())
     }
 
+    #[warn(unused_results)]
+    fn on_visit_heading(
+        &mut self,
+        _level: usize,
+        _title: &str,
+        _span: &Range<usize>,
+    ) -> Result<(), E> {
+        Ok(())
+    }
+
+    #[warn(unused_results)]
+    fn on_visit_suppression(
+        &mut self,
+        _kind: &SuppressionKind,
+        _rules: &[String],
+        _span: &Range<usize>,
+    ) -> Result<(), E> {
+        Ok(())
+    }
+
     #[warn(unused_results)]
     fn on_leave_journal(&mut self) -> Result<(), E> {
         Ok(())
@@ -239,6 +282,8 @@ pub fn walk<E>(expr: &Expr, visitor: &mut impl Visitor<E>) -> Result<(), E> {
             visitor.on_leave_journal()?;
             Ok(())
         }
+        Expr::Heading { level, title, span } => visitor.on_visit_heading(*level, title, span),
+        Expr::Suppression { kind, rules, span } => visitor.on_visit_suppression(kind, rules, span),
         Expr::Error { reason: _, span: _ } => Ok(()),
         Expr::NonTargetLine => Ok(()),
     }
@@ -5,6 +5,32 @@ use url::Url;
 
 use crate::rule::Rule;
 
+/// Severity of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Severity::Hint => "hint",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
 /// Internal diagnostic data structure.
 ///
 /// This is basically the same as `lsp_types::Diagnostic` except that this has a field
@@ -13,6 +39,7 @@ use crate::rule::Rule;
 pub struct Diagnostic {
     span: Range<usize>,
     rule: Rule,
+    severity: Severity,
     message: String,
     related_information: Option<Vec<DiagnosticRelatedInformation>>,
 }
@@ -27,11 +54,17 @@ impl Diagnostic {
         Self {
             span,
             rule,
+            severity: Severity::Warning,
             message,
             related_information,
         }
     }
 
+    /// Make a copy of this diagnostic with a different severity.
+    pub fn with_severity(self, severity: Severity) -> Self {
+        Self { severity, ..self }
+    }
+
     pub fn span(&self) -> &Range<usize> {
         &self.span
     }
@@ -40,6 +73,10 @@ impl Diagnostic {
         &self.rule
     }
 
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
     pub fn message(&self) -> &str {
         self.message.as_ref()
     }
@@ -24,3 +24,9 @@ impl InvalidTimeValueError {
 pub struct UnknownRule {
     pub(crate) rule: String,
 }
+
+#[derive(thiserror::Error, Debug)]
+#[error("Unknown rule level `{}`", .level)]
+pub struct UnknownRuleLevel {
+    pub(crate) level: String,
+}
@@ -4,3 +4,5 @@ pub mod errors;
 pub mod lint;
 pub mod parse;
 pub mod rule;
+pub mod settings;
+pub mod suppression;
@@ -6,17 +6,21 @@ use std::option::Option;
 use std::path::PathBuf;
 use std::time::Duration;
 
-use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use url::Url;
 
 use crate::ast::{walk, Expr, LooseTime, Visitor};
 use crate::diagnostic::{Diagnostic, DiagnosticRelatedInformation};
-use crate::rule::Rule;
+use crate::rule::{Rule, RuleLevel};
+use crate::settings::Settings;
+use crate::suppression::apply_suppressions;
 
 pub struct Linter<'a> {
     source: &'a Url,
+    settings: &'a Settings,
     diagnostics: Vec<Diagnostic>,
 
+    fm_span: Option<Range<usize>>,
     fm_date: Option<(NaiveDate, Range<usize>)>,
     fm_start: Option<(LooseTime, Range<usize>)>,
     fm_start_value: Option<DateTime<Utc>>,
@@ -26,15 +30,24 @@ pub struct Linter<'a> {
 
     entry_start: Option<(DateTime<Utc>, Range<usize>)>,
     entry_end: Option<(DateTime<Utc>, Range<usize>)>,
+    prev_entry_start: Option<(DateTime<Utc>, Range<usize>)>,
     prev_entry_end: Option<(DateTime<Utc>, Range<usize>)>,
+
+    /// Time ranges of the entries seen so far, along with the spans of the time ranges.
+    entry_ranges: Vec<(Range<DateTime<Utc>>, Range<usize>)>,
+
+    /// Whether the time range of any entry could not be determined.
+    has_invalid_entry_range: bool,
 }
 
 impl<'a> Linter<'a> {
-    pub fn new(source: &Url) -> Linter<'_> {
+    pub fn new(source: &'a Url, settings: &'a Settings) -> Linter<'a> {
         Linter {
             source,
+            settings,
             diagnostics: vec![],
 
+            fm_span: None,
             fm_date: None,
             fm_start: None,
             fm_start_value: None,
@@ -44,7 +57,11 @@ impl<'a> Linter<'a> {
 
             entry_start: None,
             entry_end: None,
+            prev_entry_start: None,
             prev_entry_end: None,
+
+            entry_ranges: vec![],
+            has_invalid_entry_range: false,
         }
     }
 
@@ -54,7 +71,9 @@ impl<'a> Linter<'a> {
             .file_stem()
             .and_then(|s| s.to_str())
         {
-            if let Ok(date_in_filename) = NaiveDate::parse_from_str(stem, "%Y-%m-%d") {
+            if let Ok(date_in_filename) =
+                NaiveDate::parse_from_str(stem, &self.settings.filename_date_format)
+            {
                 if date_in_filename != *value {
                     let expectation = date_in_filename.format("%Y-%m-%d").to_string();
                     self.diagnostics.push(Diagnostic::new_warning(
@@ -161,11 +180,39 @@ impl<'a> Linter<'a> {
         }
     }
 
+    /// Check if start time is not before the start of the previous entry.
+    ///
+    /// This returns whether a violation was found or not.
+    fn check_prev_start_precedes_next_start(
+        &mut self,
+        start_dt: DateTime<Utc>,
+        span: &Range<usize>,
+    ) -> bool {
+        let Some((prev_start_dt, prev_start_span)) = self.prev_entry_start.as_ref() else {
+            return false;
+        };
+        if *prev_start_dt <= start_dt {
+            return false;
+        }
+        let prev_start = self.settings.format_time(prev_start_dt);
+        self.diagnostics.push(Diagnostic::new_warning(
+            span.clone(),
+            Rule::UnsortedEntries,
+            format!("Entry starts before the previous entry, which starts at {prev_start}"),
+            Some(vec![DiagnosticRelatedInformation::new(
+                self.source.clone(),
+                prev_start_span.clone(),
+                format!("Previous entry's start time is {prev_start}"),
+            )]),
+        ));
+        true
+    }
+
     // Check if start time matches the end of the previous entry
     fn check_prev_end_equals_next_start(&mut self, start_dt: DateTime<Utc>, span: &Range<usize>) {
         if let Some((prev_end_dt, prev_end_range)) = self.prev_entry_end.as_ref() {
             if start_dt != *prev_end_dt {
-                let expectation = prev_end_dt.format("%H:%M").to_string();
+                let expectation = self.settings.format_time(prev_end_dt);
                 self.diagnostics.push(Diagnostic::new_warning(
                     span.clone(),
                     Rule::TimeJumped,
@@ -173,11 +220,7 @@ impl<'a> Linter<'a> {
                     Some(vec![DiagnosticRelatedInformation::new(
                         self.source.clone(),
                         prev_end_range.clone(),
-                        format!(
-                            "Previous entry's end time is {:02}:{:02}",
-                            prev_end_dt.hour(),
-                            prev_end_dt.minute()
-                        ),
+                        format!("Previous entry's end time is {expectation}"),
                     )]),
                 ));
             }
@@ -237,7 +280,7 @@ impl<'a> Linter<'a> {
                 Rule::NegativeTimeRange,
                 format!(
                     "End time is not ahead of start time ({})",
-                    start.format("%H:%M"),
+                    self.settings.format_time(start),
                 ),
                 None,
             ));
@@ -257,8 +300,7 @@ impl<'a> Linter<'a> {
         };
         let written = value;
         if calculated != *written {
-            let expectation = calculated.as_secs_f64() / 3600.0;
-            let expectation = format!("{expectation:1.2}");
+            let expectation = self.settings.format_duration(&calculated);
             self.diagnostics.push(Diagnostic::new_warning(
                 span.clone(),
                 Rule::IncorrectDuration,
@@ -267,6 +309,111 @@ impl<'a> Linter<'a> {
             ));
         }
     }
+
+    /// Check if any pair of entries has time ranges intersecting each other.
+    ///
+    /// The violation is reported on the latter entry of each pair.
+    fn check_entries_not_overlapping(&mut self) {
+        for (i, (range, span)) in self.entry_ranges.iter().enumerate() {
+            for (other_range, other_span) in &self.entry_ranges[..i] {
+                if range.start < other_range.end && other_range.start < range.end {
+                    let other = format!(
+                        "{}-{}",
+                        self.settings.format_time(&other_range.start),
+                        self.settings.format_time(&other_range.end),
+                    );
+                    self.diagnostics.push(Diagnostic::new_warning(
+                        span.clone(),
+                        Rule::OverlappingEntries,
+                        format!("Time range overlaps with another entry's time range {other}"),
+                        Some(vec![DiagnosticRelatedInformation::new(
+                            self.source.clone(),
+                            other_span.clone(),
+                            format!("Overlapping entry's time range is {other}"),
+                        )]),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Check if the entries account for the time between start and end in the front matter.
+    ///
+    /// Time not covered by any entry is reported as untracked, and time covered by more than one
+    /// entry is reported as double-counted.
+    ///
+    /// This is skipped if the time range of an entry is invalid, since the totals would be wrong,
+    /// and if the enabled rules which point out the gaps and overlaps themselves were violated.
+    fn check_entries_account_for_fm_range(&mut self) {
+        let (Some(fm_span), Some(fm_start), Some(fm_end)) =
+            (&self.fm_span, self.fm_start_value, self.fm_end_value)
+        else {
+            return;
+        };
+        if fm_end <= fm_start || self.has_invalid_entry_range {
+            return;
+        }
+        let restated = [
+            Rule::MismatchedStartTime,
+            Rule::MismatchedEndTime,
+            Rule::TimeJumped,
+            Rule::OverlappingEntries,
+            Rule::UnsortedEntries,
+        ];
+        if self.diagnostics.iter().any(|d| {
+            restated.contains(d.rule()) && self.settings.rule_level(d.rule()) != RuleLevel::Off
+        }) {
+            return;
+        }
+
+        // Merge time ranges of the entries to know the time covered by any of them
+        let mut ranges: Vec<Range<DateTime<Utc>>> =
+            self.entry_ranges.iter().map(|(r, _)| r.clone()).collect();
+        ranges.sort_by_key(|r| r.start);
+        let mut merged: Vec<Range<DateTime<Utc>>> = Vec::new();
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+
+        let total: TimeDelta = self.entry_ranges.iter().map(|(r, _)| r.end - r.start).sum();
+        let covered: TimeDelta = merged.iter().map(|r| r.end - r.start).sum();
+        let covered_in_fm_range: TimeDelta = merged
+            .iter()
+            .map(|r| (r.end.min(fm_end) - r.start.max(fm_start)).max(TimeDelta::zero()))
+            .sum();
+        let untracked = (fm_end - fm_start) - covered_in_fm_range;
+        let double_counted = total - covered;
+
+        let tolerance = TimeDelta::minutes(self.settings.unaccounted_time_tolerance.into());
+        if untracked <= tolerance && double_counted <= tolerance {
+            return;
+        }
+        let mut details = Vec::new();
+        for (duration, description) in
+            [(untracked, "untracked"), (double_counted, "double-counted")]
+        {
+            if TimeDelta::zero() < duration {
+                let hours = self
+                    .settings
+                    .format_duration(&duration.to_std().unwrap_or_default());
+                details.push(format!("{hours} hours {description}"));
+            }
+        }
+        self.diagnostics.push(Diagnostic::new_warning(
+            fm_span.clone(),
+            Rule::UnaccountedTime,
+            format!(
+                "Entries do not account for the time from {} to {}: {}",
+                self.settings.format_time(&fm_start),
+                self.settings.format_time(&fm_end),
+                details.join(" and "),
+            ),
+            None,
+        ));
+    }
 }
 
 impl Visitor<()> for Linter<'_> {
@@ -287,6 +434,8 @@ impl Visitor<()> for Linter<'_> {
     }
 
     fn on_leave_fm(&mut self, span: &Range<usize>) -> Result<(), ()> {
+        self.fm_span = Some(span.clone());
+
         // Calculate exact time of start and end
         self.fm_start_value = self.check_fm_start_is_valid();
         self.fm_end_value = self.check_fm_end_is_valid();
@@ -302,7 +451,10 @@ impl Visitor<()> for Linter<'_> {
     fn on_visit_start_time(&mut self, value: &LooseTime, span: &Range<usize>) -> Result<(), ()> {
         if let Some(start_dt) = self.check_start_time(value, span) {
             self.entry_start = Some((start_dt, span.clone()));
-            self.check_prev_end_equals_next_start(start_dt, span);
+            // An entry out of order always jumps in time, so report only the cause
+            if !self.check_prev_start_precedes_next_start(start_dt, span) {
+                self.check_prev_end_equals_next_start(start_dt, span);
+            }
         }
 
         if !self.seen_first_entry_start {
@@ -327,12 +479,26 @@ impl Visitor<()> for Linter<'_> {
     }
 
     fn on_leave_entry(&mut self, _span: &Range<usize>) -> Result<(), ()> {
-        self.entry_start = None;
+        if let (Some((start, start_span)), Some((end, end_span))) =
+            (&self.entry_start, &self.entry_end)
+        {
+            if start < end {
+                self.entry_ranges
+                    .push((*start..*end, start_span.start..end_span.end));
+            } else {
+                self.has_invalid_entry_range = true;
+            }
+        } else {
+            self.has_invalid_entry_range = true;
+        }
+        self.prev_entry_start = self.entry_start.take();
         self.prev_entry_end = self.entry_end.take();
         Ok(())
     }
 
     fn on_leave_journal(&mut self) -> Result<(), ()> {
+        self.check_entries_not_overlapping();
+
         // Compare end-time of front-matter and one of the last entry.
         if let (
             Some((_, fm_end_span)),
@@ -348,35 +514,117 @@ impl Visitor<()> for Linter<'_> {
                     format!(
                         "End time in the front-matter is different from the one of the last \
                          entry: expected to be {}.",
-                        last_entry_end_value.format("%H:%M")
+                        self.settings.format_time(last_entry_end_value)
                     ),
                     Some(vec![DiagnosticRelatedInformation::new(
                         self.source.clone(),
                         last_entry_end_span.clone(),
                         format!(
                             "The last entry ends with {}.",
-                            last_entry_end_value.format("%H:%M")
+                            self.settings.format_time(last_entry_end_value)
                         ),
                     )]),
                 ));
             }
         }
+
+        // Check this last since it is skipped if the checks above found a problem
+        self.check_entries_account_for_fm_range();
         Ok(())
     }
 }
 
-pub fn lint(journal: &Expr, url: &Url) -> Vec<Diagnostic> {
-    let mut visitor = Linter::new(url);
+pub fn lint(journal: &Expr, url: &Url, settings: &Settings) -> Vec<Diagnostic> {
+    let mut visitor = Linter::new(url, settings);
     walk(journal, &mut visitor).expect("walk for linting expected to succeed always.");
     visitor.diagnostics
 }
 
-pub fn parse_and_lint(url: &Url, content: &str) -> (Option<Expr>, Vec<Diagnostic>) {
-    let (journal, parse_errors) = crate::parse::parse(content);
+pub fn parse_and_lint(
+    url: &Url,
+    content: &str,
+    settings: &Settings,
+) -> (Option<Expr>, Vec<Diagnostic>) {
+    let (journal, parse_errors) = crate::parse::parse(content, settings);
     let mut diagnostics: Vec<Diagnostic> = parse_errors.iter().map(Diagnostic::from).collect();
     if let Some(journal) = &journal {
-        let mut d = lint(journal, url);
+        let mut d = lint(journal, url, settings);
         diagnostics.append(&mut d);
+        diagnostics = apply_suppressions(journal, content, diagnostics);
     };
+
+    // Apply configured rule levels, dropping violations of disabled rules
+    let diagnostics = diagnostics
+        .into_iter()
+        .filter_map(|d| {
+            let severity = settings.rule_level(d.rule()).severity()?;
+            Some(d.with_severity(severity))
+        })
+        .collect();
     (journal, diagnostics)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use rstest::*;
+
+    fn reports_unaccounted_time(content: &str, settings: &Settings) -> bool {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (_journal, diagnostics) = parse_and_lint(&url, content, settings);
+        diagnostics
+            .iter()
+            .any(|d| *d.rule() == Rule::UnaccountedTime)
+    }
+
+    #[rstest]
+    #[case(0, true)]
+    #[case(14, true)]
+    #[case(15, false)]
+    fn unaccounted_time_tolerance(#[case] tolerance: u32, #[case] reported: bool) {
+        let content = concat!(
+            "---\ndate: 2006-01-02\nstart: 09:00\nend: 11:00\n---\n",
+            "- 09:00-10:00 1.00 foo\n",
+            "- 10:15-11:00 0.75 bar\n",
+        );
+        let settings = Settings {
+            unaccounted_time_tolerance: tolerance,
+            rules: BTreeMap::from([(Rule::TimeJumped, RuleLevel::Off)]),
+            ..Default::default()
+        };
+        assert_eq!(reports_unaccounted_time(content, &settings), reported);
+    }
+
+    #[rstest]
+    #[case(RuleLevel::Off, true)]
+    #[case(RuleLevel::Hint, false)]
+    fn unaccounted_time_restating_overlap(#[case] level: RuleLevel, #[case] reported: bool) {
+        let content = concat!(
+            "---\ndate: 2006-01-02\nstart: 09:00\nend: 11:00\n---\n",
+            "- 09:00-10:15 1.25 foo\n",
+            "- 10:00-11:00 1.00 bar\n",
+        );
+        let settings = Settings {
+            rules: BTreeMap::from([(Rule::TimeJumped, level), (Rule::OverlappingEntries, level)]),
+            ..Default::default()
+        };
+        assert_eq!(reports_unaccounted_time(content, &settings), reported);
+    }
+
+    #[test]
+    fn unaccounted_time_with_invalid_time() {
+        let content = concat!(
+            "---\ndate: 2006-01-02\nstart: 09:00\nend: 11:00\n---\n",
+            "- 09:00-10:00 1.00 foo\n",
+            "- 10:00-10:450 1.00 bar\n",
+        );
+        let settings = Settings {
+            rules: BTreeMap::from([(Rule::TimeJumped, RuleLevel::Off)]),
+            ..Default::default()
+        };
+        assert!(!reports_unaccounted_time(content, &settings));
+    }
+}
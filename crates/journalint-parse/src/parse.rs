@@ -11,11 +11,12 @@ use chumsky::{
     Parser,
 };
 
-use crate::ast::{Expr, LooseTime};
+use crate::ast::{Expr, LooseTime, SuppressionKind};
+use crate::settings::Settings;
 
 /// Parse a journal file content.
-pub fn parse(content: &str) -> (Option<Expr>, Vec<Simple<char>>) {
-    journal().parse_recovery(content)
+pub fn parse(content: &str, settings: &Settings) -> (Option<Expr>, Vec<Simple<char>>) {
+    journal(settings).parse_recovery(content)
 }
 
 fn front_matter() -> impl Parser<char, Expr, Error = Simple<char>> {
@@ -158,11 +159,11 @@ fn duration() -> impl Parser<char, Expr, Error = Simple<char>> {
         .debug("duration")
 }
 
-fn code() -> impl Parser<char, Expr, Error = Simple<char>> {
+fn code(max_length: usize) -> impl Parser<char, Expr, Error = Simple<char>> {
     filter(|c: &char| c.is_ascii_alphanumeric() || *c == '-')
         .repeated()
         .at_least(1)
-        .at_most(16)
+        .at_most(max_length)
         // Make sure the word does not contain a dot to distinguish it from a `duration`
         // (This requires an essentially needless non-dot character to follow but the
         // it's fine because the journal format always require a following `duration`)
@@ -181,12 +182,17 @@ fn activity() -> impl Parser<char, Expr, Error = Simple<char>> {
         .debug("activity")
 }
 
-fn entry() -> impl Parser<char, Expr, Error = Simple<char>> {
+fn entry(settings: &Settings) -> impl Parser<char, Expr, Error = Simple<char>> {
     just('-')
         .then_ignore(wsp())
         .ignore_then(start_time().then_ignore(just('-')).then(end_time()))
         .then_ignore(wsp())
-        .then(code().then_ignore(wsp()).repeated().at_most(2))
+        .then(
+            code(settings.max_code_length)
+                .then_ignore(wsp())
+                .repeated()
+                .at_most(settings.max_codes),
+        )
         .then(duration().then_ignore(wsp()))
         .then(activity())
         .map_with_span(
@@ -201,8 +207,69 @@ fn entry() -> impl Parser<char, Expr, Error = Simple<char>> {
         )
 }
 
-fn journal() -> impl Parser<char, Expr, Error = Simple<char>> {
-    let target_line = || entry().then_ignore(newline()).debug("target_line");
+fn suppression() -> impl Parser<char, Expr, Error = Simple<char>> {
+    // Rule names are alphanumeric words joined with hyphens, which never overlaps with `-->`
+    let word = || filter(char::is_ascii_alphanumeric).repeated().at_least(1);
+    let rule = word()
+        .chain::<char, _, _>(just('-').chain(word()).repeated().flatten())
+        .collect::<String>();
+    let separator = filter(|c: &char| *c == ',' || (c.is_whitespace() && *c != '\r' && *c != '\n'))
+        .repeated()
+        .at_least(1);
+    let kind = just("journalint-disable-next-line")
+        .to(SuppressionKind::NextLine)
+        .or(just("journalint-disable").to(SuppressionKind::File));
+
+    just("<!--")
+        .then_ignore(wsp())
+        .ignore_then(kind)
+        .then(separator.ignore_then(rule).repeated())
+        .then_ignore(wsp())
+        .then_ignore(just("-->"))
+        .map_with_span(|(kind, rules), span| Expr::Suppression { kind, rules, span })
+        .debug("suppression")
+}
+
+fn heading() -> impl Parser<char, Expr, Error = Simple<char>> {
+    just('#')
+        .repeated()
+        .at_least(1)
+        .at_most(6)
+        .map(|hashes| hashes.len())
+        .then_ignore(
+            filter(|c: &char| *c == ' ' || *c == '\t')
+                .repeated()
+                .at_least(1),
+        )
+        .then(newline().not().repeated().collect::<String>())
+        .map_with_span(|(level, title), span| {
+            // Remove the optional closing sequence such as `# Title #`
+            let title = title.trim_end();
+            let stripped = title.trim_end_matches('#');
+            let title = if stripped.is_empty() || stripped.ends_with([' ', '\t']) {
+                stripped.trim_end()
+            } else {
+                title
+            };
+            Expr::Heading {
+                level,
+                title: title.to_string(),
+                span,
+            }
+        })
+        .debug("heading")
+}
+
+fn journal(settings: &Settings) -> impl Parser<char, Expr, Error = Simple<char>> {
+    let target_line = || entry(settings).then_ignore(newline()).debug("target_line");
+    let suppression_line = || {
+        wsp()
+            .ignore_then(suppression())
+            .then_ignore(wsp())
+            .then_ignore(newline())
+            .debug("suppression_line")
+    };
+    let heading_line = || heading().then_ignore(newline()).debug("heading_line");
     let non_target_line = || {
         newline()
             .not()
@@ -213,7 +280,13 @@ fn journal() -> impl Parser<char, Expr, Error = Simple<char>> {
     };
 
     front_matter()
-        .then(target_line().or(non_target_line()).repeated())
+        .then(
+            target_line()
+                .or(suppression_line())
+                .or(heading_line())
+                .or(non_target_line())
+                .repeated(),
+        )
         .then_ignore(end())
         .map(|(front_matter, lines)| Expr::Journal {
             front_matter: Box::new(front_matter),
@@ -252,6 +325,7 @@ mod tests {
         #[case] month: u32,
         #[case] day: u32,
     ) {
+        #[allow(clippy::or_then_unwrap)]
         let date = NaiveDate::from_ymd_opt(year, month, day)
             .or(Some(NaiveDate::MAX))
             .unwrap();
@@ -361,7 +435,7 @@ mod tests {
 
     #[test]
     fn code() {
-        let (result, errors) = super::code().parse_recovery_verbose("X1234567 ");
+        let (result, errors) = super::code(16).parse_recovery_verbose("X1234567 ");
         assert_eq!(errors, []);
         assert_eq!(
             result,
@@ -371,7 +445,7 @@ mod tests {
             })
         );
 
-        let (result, errors) = super::code().parse_recovery_verbose("014 ");
+        let (result, errors) = super::code(16).parse_recovery_verbose("014 ");
         assert_eq!(errors, []);
         assert_eq!(
             result,
@@ -381,7 +455,7 @@ mod tests {
             })
         );
 
-        let (result, errors) = super::code().parse_recovery_verbose("--- ");
+        let (result, errors) = super::code(16).parse_recovery_verbose("--- ");
         assert_eq!(errors, []);
         assert_eq!(
             result,
@@ -407,7 +481,7 @@ mod tests {
 
     #[test]
     fn entry_with_two_codes() {
-        let parser = super::entry();
+        let parser = super::entry(&Settings::default());
         let (entry, errors) = parser.parse_recovery_verbose(EXAMPLE_ENTRY_WITH_CODES);
         assert_eq!(errors, []);
         assert_eq!(
@@ -446,7 +520,7 @@ mod tests {
 
     #[test]
     fn entry_without_codes() {
-        let parser = super::entry();
+        let parser = super::entry(&Settings::default());
         let (entry, errors) = parser.parse_recovery_verbose(EXAMPLE_ENTRY_WITHOUT_CODES);
         assert_eq!(errors, []);
         assert_eq!(
@@ -474,6 +548,91 @@ mod tests {
         );
     }
 
+    #[test]
+    fn entry_with_codes_exceeding_limit() {
+        let input = "- 09:00-10:15 A B C 1.00 foo";
+
+        // The third code is not recognized as a code by default
+        let (entry, _errors) = super::entry(&Settings::default()).parse_recovery_verbose(input);
+        let Some(Expr::Entry { codes, .. }) = entry else {
+            panic!("expected an entry but got {entry:?}");
+        };
+        assert_eq!(codes.len(), 2);
+
+        // Recognized if configured so
+        let settings = Settings {
+            max_codes: 3,
+            ..Default::default()
+        };
+        let (entry, errors) = super::entry(&settings).parse_recovery_verbose(input);
+        assert_eq!(errors, []);
+        let Some(Expr::Entry { codes, .. }) = entry else {
+            panic!("expected an entry but got {entry:?}");
+        };
+        assert_eq!(codes.len(), 3);
+    }
+
+    #[rstest]
+    #[case("<!-- journalint-disable-next-line -->", SuppressionKind::NextLine, &[])]
+    #[case(
+        "<!-- journalint-disable-next-line time-jumped -->",
+        SuppressionKind::NextLine,
+        &["time-jumped"]
+    )]
+    #[case(
+        "<!--journalint-disable time-jumped, incorrect-duration-->",
+        SuppressionKind::File,
+        &["time-jumped", "incorrect-duration"]
+    )]
+    fn suppression(#[case] input: &str, #[case] kind: SuppressionKind, #[case] rules: &[&str]) {
+        let (result, errors) = super::suppression().parse_recovery_verbose(input);
+        assert_eq!(errors, []);
+        assert_eq!(
+            result,
+            Some(Expr::Suppression {
+                kind,
+                rules: rules.iter().map(|s| s.to_string()).collect(),
+                span: 0..input.chars().count()
+            })
+        );
+    }
+
+    #[rstest]
+    #[case("<!-- journalint-disabled -->")]
+    #[case("<!-- journalint-disable-next-line time-jumped")]
+    #[case("<!-- a comment -->")]
+    fn suppression_error(#[case] input: &str) {
+        let (result, _errors) = super::suppression().parse_recovery_verbose(input);
+        assert_eq!(result, None);
+    }
+
+    #[rstest]
+    #[case("# Morning", 1, "Morning")]
+    #[case("###\tC# review ##", 3, "C# review")]
+    #[case("## C#", 2, "C#")]
+    #[case("# ", 1, "")]
+    fn heading(#[case] input: &str, #[case] level: usize, #[case] title: &str) {
+        let (result, errors) = super::heading().parse_recovery_verbose(input);
+        assert_eq!(errors, []);
+        assert_eq!(
+            result,
+            Some(Expr::Heading {
+                level,
+                title: title.to_string(),
+                span: 0..input.chars().count()
+            })
+        );
+    }
+
+    #[rstest]
+    #[case("#hashtag")]
+    #[case("####### too deep")]
+    #[case(" # indented")]
+    fn heading_error(#[case] input: &str) {
+        let (result, _errors) = super::heading().parse_recovery_verbose(input);
+        assert_eq!(result, None);
+    }
+
     #[test]
     fn front_matter() {
         let input = concat!(
@@ -507,6 +666,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn journal_basic() {
         let input = format!(
             "---\n\
@@ -519,7 +679,7 @@ mod tests {
             ",
             EXAMPLE_ENTRY_WITH_CODES
         );
-        let (journal, errors) = super::journal().parse_recovery_verbose(input);
+        let (journal, errors) = super::journal(&Settings::default()).parse_recovery_verbose(input);
         assert_eq!(errors, []);
         assert!(journal.is_some());
         let journal = journal.unwrap();
@@ -1,8 +1,12 @@
 use core::str::FromStr;
 
-use crate::errors::UnknownRule;
+use crate::diagnostic::Severity;
+use crate::errors::{UnknownRule, UnknownRuleLevel};
 
-#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
 pub enum Rule {
     ParseError,
     MismatchedDates,
@@ -16,6 +20,10 @@ pub enum Rule {
     TimeJumped,
     NegativeTimeRange,
     IncorrectDuration,
+    OverlappingEntries,
+    UnsortedEntries,
+    UnaccountedTime,
+    UnusedSuppression,
 }
 
 impl std::fmt::Display for Rule {
@@ -39,6 +47,18 @@ impl Rule {
             Rule::TimeJumped => "time-jumped",
             Rule::NegativeTimeRange => "negative-time-range",
             Rule::IncorrectDuration => "incorrect-duration",
+            Rule::OverlappingEntries => "overlapping-entries",
+            Rule::UnsortedEntries => "unsorted-entries",
+            Rule::UnaccountedTime => "unaccounted-time",
+            Rule::UnusedSuppression => "unused-suppression",
+        }
+    }
+
+    /// Get the level of the rule used unless configured otherwise.
+    pub fn default_level(&self) -> RuleLevel {
+        match self {
+            Rule::ParseError => RuleLevel::Error,
+            _ => RuleLevel::Warning,
         }
     }
 }
@@ -51,6 +71,7 @@ impl FromStr for Rule {
             "parse-error" => Ok(Rule::ParseError),
             "date-mismatch" => Ok(Rule::MismatchedDates),
             "starttime-mismatch" => Ok(Rule::MismatchedStartTime),
+            "endtime-mismatch" => Ok(Rule::MismatchedEndTime),
             "invalid-start-time" => Ok(Rule::InvalidStartTime),
             "invalid-end-time" => Ok(Rule::InvalidEndTime),
             "missing-date" => Ok(Rule::MissingDate),
@@ -59,6 +80,10 @@ impl FromStr for Rule {
             "time-jumped" => Ok(Rule::TimeJumped),
             "negative-time-range" => Ok(Rule::NegativeTimeRange),
             "incorrect-duration" => Ok(Rule::IncorrectDuration),
+            "overlapping-entries" => Ok(Rule::OverlappingEntries),
+            "unsorted-entries" => Ok(Rule::UnsortedEntries),
+            "unaccounted-time" => Ok(Rule::UnaccountedTime),
+            "unused-suppression" => Ok(Rule::UnusedSuppression),
             _ => Err(UnknownRule {
                 rule: s.to_string(),
             }),
@@ -66,6 +91,61 @@ impl FromStr for Rule {
     }
 }
 
+impl TryFrom<String> for Rule {
+    type Error = UnknownRule;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Rule::from_str(&value)
+    }
+}
+
+impl From<Rule> for String {
+    fn from(value: Rule) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+/// Level of a rule, which determines the severity of its violations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Hint,
+    Info,
+    Warning,
+    Error,
+}
+
+impl RuleLevel {
+    /// Get severity of violations of a rule at this level, or `None` if the rule is disabled.
+    pub fn severity(&self) -> Option<Severity> {
+        match self {
+            RuleLevel::Off => None,
+            RuleLevel::Hint => Some(Severity::Hint),
+            RuleLevel::Info => Some(Severity::Info),
+            RuleLevel::Warning => Some(Severity::Warning),
+            RuleLevel::Error => Some(Severity::Error),
+        }
+    }
+}
+
+impl FromStr for RuleLevel {
+    type Err = UnknownRuleLevel;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(RuleLevel::Off),
+            "hint" => Ok(RuleLevel::Hint),
+            "info" => Ok(RuleLevel::Info),
+            "warning" => Ok(RuleLevel::Warning),
+            "error" => Ok(RuleLevel::Error),
+            _ => Err(UnknownRuleLevel {
+                level: s.to_string(),
+            }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,6 +155,8 @@ mod tests {
     #[rstest]
     #[case("parse-error", true)]
     #[case("date-mismatch", true)]
+    #[case("starttime-mismatch", true)]
+    #[case("endtime-mismatch", true)]
     #[case("invalid-start-time", true)]
     #[case("invalid-end-time", true)]
     #[case("missing-date", true)]
@@ -83,6 +165,10 @@ mod tests {
     #[case("time-jumped", true)]
     #[case("negative-time-range", true)]
     #[case("incorrect-duration", true)]
+    #[case("overlapping-entries", true)]
+    #[case("unsorted-entries", true)]
+    #[case("unaccounted-time", true)]
+    #[case("unused-suppression", true)]
     #[case("foobar", false)]
     fn string_conversion(#[case] s: &'static str, #[case] ok: bool) {
         let result = Rule::from_str(s);
@@ -93,4 +179,15 @@ mod tests {
             assert_eq!(format!("{rule}"), s);
         }
     }
+
+    #[rstest]
+    #[case("off", Some(RuleLevel::Off))]
+    #[case("hint", Some(RuleLevel::Hint))]
+    #[case("info", Some(RuleLevel::Info))]
+    #[case("warning", Some(RuleLevel::Warning))]
+    #[case("error", Some(RuleLevel::Error))]
+    #[case("warn", None)]
+    fn rule_level_from_str(#[case] s: &'static str, #[case] want: Option<RuleLevel>) {
+        assert_eq!(RuleLevel::from_str(s).ok(), want);
+    }
 }
@@ -0,0 +1,169 @@
+//! Provides settings which customize parsing, linting and formatting.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::ast::LooseTime;
+use crate::rule::{Rule, RuleLevel};
+
+/// Settings of journalint.
+///
+/// This is typically loaded from a configuration file named `journalint.toml`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Settings {
+    /// Format of the date embedded in filenames of journal files.
+    pub filename_date_format: String,
+
+    /// Maximum number of characters in a code.
+    pub max_code_length: usize,
+
+    /// Maximum number of codes in an entry.
+    pub max_codes: usize,
+
+    /// Format of time values written by journalint.
+    pub time_format: String,
+
+    /// Number of digits after the decimal point of durations written by journalint.
+    pub duration_precision: usize,
+
+    /// Minutes of untracked or double-counted time to tolerate in a day.
+    pub unaccounted_time_tolerance: u32,
+
+    /// Whether to align codes and durations of entries in columns on formatting.
+    pub align_columns: bool,
+
+    /// Levels of rules overriding their default levels.
+    pub rules: BTreeMap<Rule, RuleLevel>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            filename_date_format: "%Y-%m-%d".to_string(),
+            max_code_length: 16,
+            max_codes: 2,
+            time_format: "%H:%M".to_string(),
+            duration_precision: 2,
+            unaccounted_time_tolerance: 0,
+            align_columns: false,
+            rules: BTreeMap::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Get the level of a rule.
+    pub fn rule_level(&self, rule: &Rule) -> RuleLevel {
+        self.rules
+            .get(rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_level())
+    }
+
+    /// Format a time value according to `time_format`.
+    ///
+    /// The default format is used instead if `time_format` is invalid.
+    pub fn format_time(&self, value: &DateTime<Utc>) -> String {
+        let mut text = String::new();
+        match write!(text, "{}", value.format(&self.time_format)) {
+            Ok(()) => text,
+            Err(_) => value.format(&Settings::default().time_format).to_string(),
+        }
+    }
+
+    /// Format a duration in hours according to `duration_precision`.
+    pub fn format_duration(&self, value: &Duration) -> String {
+        let hours = value.as_secs_f64() / 3600.0;
+        format!("{hours:1.*}", self.duration_precision)
+    }
+}
+
+/// Check whether time values written in a format string can be read back by the parser.
+pub fn is_valid_time_format(format: &str) -> bool {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return false;
+    }
+    let date = NaiveDate::default();
+    [(0, 0), (9, 5), (12, 30), (13, 45), (23, 59)]
+        .into_iter()
+        .filter_map(|(h, m)| date.and_hms_opt(h, m, 0))
+        .all(|value| {
+            let value = value.and_utc();
+            let text = value.format(format).to_string();
+            LooseTime::new(text)
+                .to_datetime(date)
+                .is_ok_and(|t| t == value)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::NaiveDate;
+    use rstest::*;
+
+    #[rstest]
+    #[case(2, 4500, "1.25")]
+    #[case(2, 4520, "1.26")]
+    #[case(1, 4500, "1.2")]
+    #[case(0, 4500, "1")]
+    fn format_duration(#[case] precision: usize, #[case] secs: u64, #[case] want: &str) {
+        let settings = Settings {
+            duration_precision: precision,
+            ..Default::default()
+        };
+        assert_eq!(settings.format_duration(&Duration::from_secs(secs)), want);
+    }
+
+    #[test]
+    fn rule_level() {
+        let settings = Settings {
+            rules: BTreeMap::from([(Rule::TimeJumped, RuleLevel::Off)]),
+            ..Default::default()
+        };
+        assert_eq!(settings.rule_level(&Rule::TimeJumped), RuleLevel::Off);
+        assert_eq!(settings.rule_level(&Rule::ParseError), RuleLevel::Error);
+        assert_eq!(
+            settings.rule_level(&Rule::IncorrectDuration),
+            RuleLevel::Warning
+        );
+    }
+
+    #[test]
+    fn format_time() {
+        let settings = Settings {
+            time_format: "%-H:%M".to_string(),
+            ..Default::default()
+        };
+        let value = NaiveDate::from_ymd_opt(2006, 1, 2)
+            .and_then(|d| d.and_hms_opt(9, 5, 0))
+            .unwrap()
+            .and_utc();
+        assert_eq!(settings.format_time(&value), "9:05");
+        assert_eq!(Settings::default().format_time(&value), "09:05");
+
+        let settings = Settings {
+            time_format: "%Q".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(settings.format_time(&value), "09:05");
+    }
+
+    #[rstest]
+    #[case("%H:%M", true)]
+    #[case("%-H:%M", true)]
+    #[case("%-I:%M %p", false)]
+    #[case("%I:%M", false)]
+    #[case("%H.%M", false)]
+    #[case("%H:%M:%S", false)]
+    #[case("%Q", false)]
+    #[case("%H:%", false)]
+    fn is_valid_time_format(#[case] format: &str, #[case] want: bool) {
+        assert_eq!(super::is_valid_time_format(format), want);
+    }
+}
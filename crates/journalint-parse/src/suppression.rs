@@ -0,0 +1,261 @@
+//! Provides filtering of diagnostics by suppression comments.
+//!
+//! A suppression comment is an HTML comment on its own line such as below:
+//!
+//! - `<!-- journalint-disable-next-line time-jumped -->` suppresses violations of the listed
+//!   rules on the next line.
+//! - `<!-- journalint-disable incorrect-duration -->` suppresses violations of the listed rules
+//!   in the whole file.
+//!
+//! Violations of any rule are suppressed if no rule is listed.
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::ast::{walk, Expr, SuppressionKind, Visitor};
+use crate::diagnostic::Diagnostic;
+use crate::rule::Rule;
+
+struct Suppression {
+    kind: SuppressionKind,
+    rules: Vec<String>,
+    span: Range<usize>,
+
+    /// Line index of the suppression comment.
+    line: usize,
+
+    /// Whether each of `rules` suppressed something, or whether this suppressed something if
+    /// no rule is listed.
+    used: Vec<bool>,
+}
+
+impl Suppression {
+    /// Suppress the diagnostic if applicable, and return whether it was suppressed or not.
+    fn suppress(&mut self, diagnostic: &Diagnostic, line: usize) -> bool {
+        if self.kind == SuppressionKind::NextLine && line != self.line + 1 {
+            return false;
+        }
+        if self.rules.is_empty() {
+            self.used[0] = true;
+            return true;
+        }
+        let Some(index) = self
+            .rules
+            .iter()
+            .position(|r| r == diagnostic.rule().as_str())
+        else {
+            return false;
+        };
+        self.used[index] = true;
+        true
+    }
+
+    /// Make diagnostics for the unused part of this suppression.
+    fn unused_diagnostics(&self) -> Vec<Diagnostic> {
+        let new_diagnostic = |message| {
+            Diagnostic::new_warning(self.span.clone(), Rule::UnusedSuppression, message, None)
+        };
+        if self.rules.is_empty() {
+            if self.used[0] {
+                return vec![];
+            }
+            return vec![new_diagnostic(
+                "Unused suppression: no rule violation to suppress".to_string(),
+            )];
+        }
+        self.rules
+            .iter()
+            .zip(self.used.iter())
+            .filter(|(_, &used)| !used)
+            .map(|(rule, _)| match Rule::from_str(rule) {
+                Ok(_) => new_diagnostic(format!(
+                    "Unused suppression: no violation of `{rule}` to suppress"
+                )),
+                Err(_) => new_diagnostic(format!("Unused suppression: unknown rule `{rule}`")),
+            })
+            .collect()
+    }
+}
+
+struct SuppressionCollector<'a> {
+    line_offsets: &'a [usize],
+    suppressions: Vec<Suppression>,
+}
+
+impl Visitor<()> for SuppressionCollector<'_> {
+    fn on_visit_suppression(
+        &mut self,
+        kind: &SuppressionKind,
+        rules: &[String],
+        span: &Range<usize>,
+    ) -> Result<(), ()> {
+        self.suppressions.push(Suppression {
+            kind: *kind,
+            rules: rules.to_vec(),
+            span: span.clone(),
+            line: line_index(self.line_offsets, span.start),
+            used: vec![false; rules.len().max(1)],
+        });
+        Ok(())
+    }
+}
+
+/// Remove diagnostics suppressed by suppression comments in the journal.
+///
+/// Diagnostics for the unused suppressions are appended to the result.
+pub fn apply_suppressions(
+    journal: &Expr,
+    content: &str,
+    diagnostics: Vec<Diagnostic>,
+) -> Vec<Diagnostic> {
+    // Collect suppression comments
+    let line_offsets = line_offsets(content);
+    let mut visitor = SuppressionCollector {
+        line_offsets: &line_offsets,
+        suppressions: Vec::new(),
+    };
+    walk(journal, &mut visitor).expect("walk for suppressions expected to succeed always.");
+    let mut suppressions = visitor.suppressions;
+    if suppressions.is_empty() {
+        return diagnostics;
+    }
+
+    // Filter out the suppressed diagnostics
+    let mut diagnostics: Vec<Diagnostic> = diagnostics
+        .into_iter()
+        .filter(|d| {
+            let line = line_index(&line_offsets, d.span().start);
+            // Let every suppression see the diagnostic to mark all relevant ones as used
+            let mut suppressed = false;
+            for suppression in suppressions.iter_mut() {
+                suppressed |= suppression.suppress(d, line);
+            }
+            !suppressed
+        })
+        .collect();
+    for suppression in &suppressions {
+        diagnostics.append(&mut suppression.unused_diagnostics());
+    }
+    diagnostics
+}
+
+/// Calculate offsets (in chars) of the beginning of each line.
+fn line_offsets(content: &str) -> Vec<usize> {
+    let mut offsets = vec![0];
+    let mut chars = content.chars().enumerate().peekable();
+    while let Some((offset, ch)) = chars.next() {
+        if ch == '\n' || (ch == '\r' && chars.peek().map(|(_, c)| *c) != Some('\n')) {
+            offsets.push(offset + 1);
+        }
+    }
+    offsets
+}
+
+/// Get the index of the line containing the offset.
+fn line_index(line_offsets: &[usize], offset: usize) -> usize {
+    line_offsets.partition_point(|&o| o <= offset) - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+
+    use url::Url;
+
+    use crate::lint::parse_and_lint;
+    use crate::rule::RuleLevel;
+    use crate::settings::Settings;
+
+    fn lint(body: &str) -> Vec<(String, usize)> {
+        let content = format!(
+            "---\ndate: 2006-01-02\nstart: 09:00\nend: 12:00\n---\n{}",
+            body
+        );
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        // Journals in these tests have gaps on purpose to cause `time-jumped`
+        let settings = Settings {
+            rules: BTreeMap::from([(Rule::UnaccountedTime, RuleLevel::Off)]),
+            ..Default::default()
+        };
+        let (_journal, diagnostics) = parse_and_lint(&url, &content, &settings);
+        let line_offsets = super::line_offsets(&content);
+        diagnostics
+            .iter()
+            .map(|d| {
+                let line = line_index(&line_offsets, d.span().start);
+                (d.rule().as_str().to_string(), line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn line_offsets() {
+        assert_eq!(super::line_offsets("a\n亜\r\nc\rd"), vec![0, 2, 5, 7]);
+    }
+
+    #[test]
+    fn no_suppression() {
+        let diagnostics = lint(concat!(
+            "- 09:00-10:00 1.00 foo\n",
+            "- 11:00-12:00 1.00 bar\n",
+        ));
+        assert_eq!(diagnostics, vec![("time-jumped".to_string(), 6)]);
+    }
+
+    #[test]
+    fn next_line() {
+        let diagnostics = lint(concat!(
+            "- 09:00-10:00 1.00 foo\n",
+            "<!-- journalint-disable-next-line time-jumped -->\n",
+            "- 11:00-12:00 1.00 bar\n",
+        ));
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn next_line_without_rules() {
+        let diagnostics = lint(concat!(
+            "- 09:00-10:00 1.00 foo\n",
+            "<!-- journalint-disable-next-line -->\n",
+            "- 11:00-12:00 2.00 bar\n",
+        ));
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn next_line_for_another_rule() {
+        let diagnostics = lint(concat!(
+            "- 09:00-10:00 1.00 foo\n",
+            "<!-- journalint-disable-next-line incorrect-duration -->\n",
+            "- 11:00-12:00 1.00 bar\n",
+        ));
+        assert_eq!(
+            diagnostics,
+            vec![
+                ("time-jumped".to_string(), 7),
+                ("unused-suppression".to_string(), 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn file() {
+        let diagnostics = lint(concat!(
+            "<!-- journalint-disable time-jumped, incorrect-duration -->\n",
+            "- 09:00-10:00 2.00 foo\n",
+            "- 11:00-12:00 1.00 bar\n",
+        ));
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn unknown_rule() {
+        let diagnostics = lint(concat!(
+            "<!-- journalint-disable time-jumped no-such-rule -->\n",
+            "- 09:00-10:00 1.00 foo\n",
+            "- 11:00-12:00 1.00 bar\n",
+        ));
+        assert_eq!(diagnostics, vec![("unused-suppression".to_string(), 5)]);
+    }
+}
@@ -1,18 +1,65 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::NaiveDate;
 use clap::Parser;
+use journalint_parse::rule::{Rule, RuleLevel};
 
-use crate::cli::export::ExportFormat;
+use crate::cli::export::{EntryFilter, ExportFormat, Pattern};
+use crate::cli::import::ImportFormat;
 use crate::cli::report::ReportFormat;
+use crate::cli::summary::SummaryFormat;
+
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  No errors found, and the number of warnings does not exceed --max-warnings
+  1  Errors found, warnings exceeding --max-warnings found, or files not formatted
+     found with --format --check
+  2  Usage error, I/O error or any other failure";
 
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[command(author, version, about, after_help = EXIT_STATUS_HELP)]
 pub(crate) struct Arguments {
-    /// File to lint.
-    pub filename: Option<String>,
+    /// Files, directories or glob patterns to lint.
+    ///
+    /// Directories are searched recursively for files named like `YYYY-MM-DD.md`. On `--import`,
+    /// these are files to import.
+    pub filenames: Vec<String>,
+
+    /// Use the specified configuration file instead of searching `journalint.toml`.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<String>,
 
     /// Fix found problems.
     #[arg(short, long)]
     pub fix: bool,
 
+    /// Format files instead of linting them.
+    ///
+    /// Time values are written in `time-format`, durations in `duration-precision` and fields of
+    /// entries are separated by a single space (or aligned if `align-columns` is enabled).
+    #[arg(long, conflicts_with_all = ["fix", "export"])]
+    pub format: bool,
+
+    /// Check whether files are formatted without writing them, on `--format`.
+    #[arg(long, requires = "format")]
+    pub check: bool,
+
+    /// Number of warnings to tolerate before exiting with a non-zero status.
+    ///
+    /// When omitted, any warning makes the exit status non-zero. Errors always make it non-zero
+    /// and hints or information never do. This also applies to the violations remaining after
+    /// `--fix` and the ones found on `--export`.
+    #[arg(long, value_name = "N")]
+    pub max_warnings: Option<usize>,
+
+    /// Set the level of a rule to one of off, hint, info, warning and error.
+    ///
+    /// This overrides the level in the configuration file and can be specified multiple times
+    /// (e.g. `--rule time-jumped=off --rule incorrect-duration=error`).
+    #[arg(long = "rule", value_name = "RULE=LEVEL", value_parser = parse_rule_level)]
+    pub rules: Vec<(Rule, RuleLevel)>,
+
     /// Report rule violations in the specified format.
     #[clap(value_enum)]
     #[arg(long, value_name = "FORMAT", default_value_t = ReportFormat::Fancy)]
@@ -26,7 +73,106 @@ pub(crate) struct Arguments {
     #[arg(long, default_value_t = false)]
     pub extract_activity_prefixes: bool,
 
+    /// Print total durations of journal entries in the specified format.
+    ///
+    /// Durations written in entries are summed up per code, per activity prefix, per day, per ISO
+    /// week and per month.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        conflicts_with_all = ["fix", "format", "export", "extract_activity_prefixes"]
+    )]
+    pub summary: Option<SummaryFormat>,
+
+    /// Export or summarize only journals dated on or after the date (YYYY-MM-DD).
+    #[arg(long, value_name = "DATE")]
+    pub since: Option<NaiveDate>,
+
+    /// Export or summarize only journals dated on or before the date (YYYY-MM-DD).
+    #[arg(long, value_name = "DATE")]
+    pub until: Option<NaiveDate>,
+
+    /// Export or summarize only entries having a code which matches the pattern.
+    ///
+    /// A pattern is a glob such as `AB*` matching the whole code, or a regular expression enclosed
+    /// in slashes such as `/^AB[0-9]+$/`. This can be specified multiple times to accept entries
+    /// matching any of them.
+    #[arg(long = "code", value_name = "PATTERN")]
+    pub codes: Vec<Pattern>,
+
+    /// Export or summarize only entries having no code which matches the pattern.
+    #[arg(long = "exclude-code", value_name = "PATTERN")]
+    pub excluded_codes: Vec<Pattern>,
+
+    /// Export or summarize only entries having an activity prefix which matches the pattern.
+    ///
+    /// Prefixes of an activity are the parts followed by `: `, e.g. `foo` and `bar` of
+    /// `foo: bar: baz`. Patterns are written in the same way as `--code`.
+    #[arg(long = "activity-prefix", value_name = "PATTERN")]
+    pub activity_prefixes: Vec<Pattern>,
+
+    /// Export or summarize only entries having no activity prefix which matches the pattern.
+    #[arg(long = "exclude-activity-prefix", value_name = "PATTERN")]
+    pub excluded_activity_prefixes: Vec<Pattern>,
+
+    /// Export or summarize only entries whose durations are the hours or longer (e.g. 0.25).
+    #[arg(long, value_name = "HOURS", value_parser = parse_hours)]
+    pub min_duration: Option<Duration>,
+
+    /// Import entries from files in the specified format into journal files, one per day.
+    ///
+    /// Each journal file is verified to be read back as the imported entries without errors
+    /// before any file is written.
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        conflicts_with_all = ["fix", "format", "export", "summary"]
+    )]
+    pub import: Option<ImportFormat>,
+
+    /// Directory to write journal files in on `--import` [default: current directory].
+    #[arg(long, value_name = "DIR", requires = "import")]
+    pub output_dir: Option<String>,
+
+    /// Overwrite existing journal files on `--import`.
+    #[arg(long, requires = "import")]
+    pub overwrite: bool,
+
     /// Start as a language server.
     #[arg(long)]
     pub stdio: bool,
 }
+
+impl Arguments {
+    /// Make the conditions of entries to export or summarize.
+    pub fn entry_filter(&self) -> EntryFilter {
+        EntryFilter {
+            since: self.since,
+            until: self.until,
+            codes: self.codes.clone(),
+            excluded_codes: self.excluded_codes.clone(),
+            activity_prefixes: self.activity_prefixes.clone(),
+            excluded_activity_prefixes: self.excluded_activity_prefixes.clone(),
+            min_duration: self.min_duration,
+        }
+    }
+}
+
+fn parse_rule_level(s: &str) -> Result<(Rule, RuleLevel), String> {
+    let (rule, level) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected RULE=LEVEL but got `{s}`"))?;
+    let rule = Rule::from_str(rule).map_err(|e| e.to_string())?;
+    let level = RuleLevel::from_str(level).map_err(|e| e.to_string())?;
+    Ok((rule, level))
+}
+
+fn parse_hours(s: &str) -> Result<Duration, String> {
+    let hours = str::parse::<f64>(s).map_err(|e| e.to_string())?;
+    if !hours.is_finite() || hours < 0.0 {
+        return Err(format!(
+            "expected a non-negative number of hours but got `{s}`"
+        ));
+    }
+    Ok(Duration::from_secs_f64(hours * 3600.0))
+}
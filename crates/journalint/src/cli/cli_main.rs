@@ -1,125 +1,496 @@
-use std::fs::{read_to_string, write};
-use std::io;
-use std::path::PathBuf;
+use std::env::current_dir;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::io::{self, Write};
+use std::path::{absolute, Path, PathBuf};
 use std::sync::Arc;
 
-use journalint_parse::ast;
-use journalint_parse::diagnostic::Diagnostic;
+use journalint_parse::diagnostic::{Diagnostic, Severity};
 use journalint_parse::lint::parse_and_lint;
+use journalint_parse::parse::parse;
 use journalint_parse::rule::Rule;
+use journalint_parse::settings::Settings;
+use log::error;
 use lsp_types::Url;
 
 use crate::cli::arg::Arguments;
-use crate::cli::export::{export, ExportFormat};
-use crate::cli::report::{report, ReportFormat};
-use crate::commands::{AutofixCommand, Command};
-use crate::errors::{CliError, JournalintError};
+use crate::cli::export::{collect_entries, export, EntryFilter, JournalEntry};
+use crate::cli::files::collect_files;
+use crate::cli::import::{group_by_date, read_entries, ImportFormat, ImportedJournal};
+use crate::cli::report::{report, ReportFormat, Reporter};
+use crate::cli::summary::{summarize, write_summary};
+use crate::commands::fix_all;
+use crate::config::resolve_settings;
+use crate::errors::{CliError, E_IOERR, E_UNEXPECTED, E_USAGE, E_VIOLATIONS};
+use crate::formatter::format_content;
+use crate::journal_info::JournalInfo;
 use crate::line_mapper::LineMapper;
 
-const E_UNEXPECTED: exitcode::ExitCode = 1;
-
 pub(crate) fn main(args: Arguments) -> Result<(), CliError> {
-    // Make sure a filename was given
-    let filename = args.filename.ok_or(
-        CliError::new(exitcode::USAGE).with_message("FILENAME must be specified.".to_string()),
-    )?;
-    let path = PathBuf::from(&filename).canonicalize().map_err(|e| {
-        CliError::new(exitcode::IOERR).with_message(format!(
+    // Make sure at least one filename was given
+    if args.filenames.is_empty() {
+        return Err(CliError::new(E_USAGE).with_message("FILENAME must be specified.".to_string()));
+    }
+
+    // Determine settings for the current directory, which are used for options spanning files
+    let cwd = current_dir().map_err(|e| CliError::new(E_IOERR).with_message(e.to_string()))?;
+    let settings = settings_for(&args, &cwd)?;
+
+    // Import files into journal files rather than reading journal files
+    if let Some(import_format) = &args.import {
+        return main_import(&args, import_format, &settings);
+    }
+
+    // Expand directories and glob patterns into a list of journal files
+    let config = args.config.as_deref().map(Path::new);
+    let paths = collect_files(&args.filenames, config)?;
+
+    // Format the files instead of linting them
+    if args.format {
+        return main_format(&args, &paths);
+    }
+
+    // Process each file, continuing with the rest even if one of them failed
+    let mut summary = Summary::default();
+    let mut first_error: Option<CliError> = None;
+    let mut collected = CollectedEntries::default();
+    let mut reporter = Reporter::new(args.report, io::stdout());
+    for path in &paths {
+        match process_file(&args, path, &mut collected, &mut reporter) {
+            Ok(severities) => summary.add(&severities),
+            Err(e) => {
+                if let Some(msg) = e.message() {
+                    error!("{}", msg);
+                }
+                first_error.get_or_insert(CliError::new(e.exit_status()));
+            }
+        }
+    }
+
+    // Export entries collected from all the files to stdout
+    let entries = &collected.entries;
+    if let Some(export_format) = &args.export {
+        export(
+            export_format.clone(),
+            entries,
+            collected.max_codes.unwrap_or(settings.max_codes),
+            &mut io::stdout(),
+        )
+        .map_err(|e| {
+            CliError::new(E_UNEXPECTED).with_message(format!("Failed to export data: {:?}", e))
+        })?;
+    }
+
+    // Print totals of entries collected from all the files to stdout
+    if let Some(summary_format) = &args.summary {
+        write_summary(
+            summary_format.clone(),
+            &summarize(entries),
+            &settings,
+            &mut io::stdout(),
+        )
+        .map_err(|e| {
+            CliError::new(E_UNEXPECTED).with_message(format!("Failed to write summary: {:?}", e))
+        })?;
+    }
+
+    // Write reports held until all the files are processed (e.g. SARIF)
+    reporter
+        .finish()
+        .map_err(|e| CliError::new(E_IOERR).with_message(e.to_string()))?;
+
+    // Write a summary of all the files processed unless it breaks machine-readable output
+    if args.export.is_some() || args.summary.is_some() {
+        summary.write(io::stderr())
+    } else if args.report.is_human_readable() {
+        summary.write(io::stdout())
+    } else {
+        Ok(())
+    }
+    .map_err(|e| CliError::new(E_IOERR).with_message(e.to_string()))?;
+
+    // Determine the exit status; errors take precedence over rule violations
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+    if 0 < summary.num_errors || args.max_warnings.unwrap_or(0) < summary.num_warnings {
+        return Err(CliError::new(E_VIOLATIONS));
+    }
+    Ok(())
+}
+
+/// Determine settings for a journal file or a directory, applying the command line options.
+fn settings_for(args: &Arguments, path: &Path) -> Result<Settings, CliError> {
+    let config = args.config.as_deref().map(Path::new);
+    let mut settings = resolve_settings(config, path, &Settings::default())
+        .map_err(|e| CliError::new(E_USAGE).with_message(e.to_string()))?;
+    settings.rules.extend(args.rules.iter().cloned());
+    Ok(settings)
+}
+
+/// Entries collected from journal files to export or summarize.
+#[derive(Debug, Default)]
+struct CollectedEntries {
+    entries: Vec<JournalEntry>,
+
+    /// Maximum of `max-codes` in the settings of the journal files, if any was collected.
+    max_codes: Option<usize>,
+}
+
+/// Process a file and return severities of the problems found in it.
+///
+/// On exporting or summarizing, entries are collected into `collected`. On reporting, diagnostics are
+/// passed to `reporter`.
+fn process_file(
+    args: &Arguments,
+    path: &Path,
+    collected: &mut CollectedEntries,
+    reporter: &mut Reporter<impl Write>,
+) -> Result<Vec<Severity>, CliError> {
+    let filename = path.to_string_lossy();
+    let abs_path = path.canonicalize().map_err(|e| {
+        CliError::new(E_IOERR).with_message(format!(
             "Failed to canonicalize the filename {filename:?}: {e:?}"
         ))
     })?;
-    let url = Url::from_file_path(path.clone()).map_err(|_| {
+    let url = Url::from_file_path(abs_path.clone()).map_err(|_| {
         CliError::new(E_UNEXPECTED)
-            .with_message(format!("Failed to compose URL from path {:?}", &path))
+            .with_message(format!("Failed to compose URL from path {:?}", &abs_path))
     })?;
 
-    // Load the content
-    let content = read_to_string(&path).map_err(|e| {
-        CliError::new(exitcode::IOERR).with_message(format!("Failed to read {filename:?}: {e:?}"))
+    // Load the content and the settings for it
+    let content = read_to_string(&abs_path).map_err(|e| {
+        CliError::new(E_IOERR).with_message(format!("Failed to read {filename:?}: {e:?}"))
     })?;
+    let settings = settings_for(args, &abs_path)?;
 
     // Execute specified task against the AST and diagnostics
     if args.fix {
-        main_fix(&filename, &url, &content)?;
-    } else if let Some(export_format) = args.export {
+        main_fix(&filename, &url, &content, &settings, reporter)
+    } else if args.export.is_some() || args.summary.is_some() {
+        let filter = args.entry_filter();
         main_export(
             &filename,
             &url,
             &content,
-            export_format,
+            &settings,
             args.extract_activity_prefixes,
-        )?;
+            &filter,
+            collected,
+        )
     } else {
-        main_report(&filename, &url, &content, args.report)?;
+        main_report(&filename, &url, &content, &settings, reporter)
+    }
+}
+
+/// Format files, or check whether they are formatted on `--check`.
+fn main_format(args: &Arguments, paths: &[PathBuf]) -> Result<(), CliError> {
+    let mut num_unformatted = 0;
+    let mut first_error: Option<CliError> = None;
+    for path in paths {
+        match format_file(args, path) {
+            Ok(true) => {}
+            Ok(false) => num_unformatted += 1,
+            Err(e) => {
+                if let Some(msg) = e.message() {
+                    error!("{}", msg);
+                }
+                first_error.get_or_insert(CliError::new(e.exit_status()));
+            }
+        }
     }
 
+    if 0 < num_unformatted {
+        let verb = if args.check {
+            "would be reformatted"
+        } else {
+            "reformatted"
+        };
+        println!(
+            "{} {verb} ({} checked).",
+            plural(num_unformatted, "file"),
+            plural(paths.len(), "file")
+        );
+    }
+
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+    if args.check && 0 < num_unformatted {
+        return Err(CliError::new(E_VIOLATIONS));
+    }
     Ok(())
 }
 
-fn main_fix(filename: &str, url: &Url, content: &str) -> Result<(), CliError> {
-    let remaining_diagnostics;
+/// Format a file unless `--check` is given, and return whether it was formatted already.
+fn format_file(args: &Arguments, path: &Path) -> Result<bool, CliError> {
+    let filename = path.to_string_lossy();
+    let content = read_to_string(path).map_err(|e| {
+        CliError::new(E_IOERR).with_message(format!("Failed to read {filename:?}: {e:?}"))
+    })?;
+    let settings = settings_for(args, path)?;
+
+    let (Some(journal), _errors) = parse(&content, &settings) else {
+        return Err(CliError::new(E_VIOLATIONS)
+            .with_message(format!("Failed to format {filename:?} due to parse errors")));
+    };
+    let formatted = format_content(&content, &journal, &settings).map_err(|e| {
+        CliError::new(E_UNEXPECTED).with_message(format!("Failed to format {filename:?}: {e:?}"))
+    })?;
+    let Some(formatted) = formatted else {
+        return Ok(true);
+    };
 
-    // Create a working copy of the content.
-    let mut buffer = String::with_capacity(content.len() + 128);
-    buffer.push_str(content);
+    if args.check {
+        println!("Would reformat {filename}");
+    } else {
+        write(path, formatted).map_err(|e| {
+            CliError::new(E_IOERR).with_message(format!("Failed to write {filename:?}: {e:?}"))
+        })?;
+        println!("Reformatted {filename}");
+    }
+    Ok(false)
+}
+
+/// Import entries into journal files, writing them only if all of them are verified.
+fn main_import(
+    args: &Arguments,
+    import_format: &ImportFormat,
+    settings: &Settings,
+) -> Result<(), CliError> {
+    // Read entries from all the files
+    let mut entries = Vec::new();
+    for filename in &args.filenames {
+        let content = read_to_string(filename).map_err(|e| {
+            CliError::new(E_IOERR).with_message(format!("Failed to read {filename:?}: {e:?}"))
+        })?;
+        let mut file_entries =
+            read_entries(import_format.clone(), &content, settings).map_err(|e| {
+                CliError::new(E_USAGE).with_message(format!("Failed to import {filename:?}: {e}"))
+            })?;
+        entries.append(&mut file_entries);
+    }
 
-    // Repeatedly execute parse, lint, and fix until no fix is done.
-    let mut num_fixed = 0;
-    'outer: loop {
-        let (journal, diagnostics) = parse_and_lint(url, &buffer);
-        for diagnostic in diagnostics.iter().as_ref() {
-            let fixed =
-                fix_violation(url, journal.as_ref(), diagnostic, &mut buffer).map_err(|e| {
-                    CliError::new(E_UNEXPECTED)
-                        .with_message(format!("Failed on fixing a rule violation: {e:?}"))
-                })?;
-            if fixed {
-                num_fixed += 1;
-                continue 'outer;
+    // Determine settings for the directory to write journal files in, which may not exist yet
+    let output_dir = Path::new(args.output_dir.as_deref().unwrap_or("."));
+    let settings = settings_for(args, output_dir)?;
+
+    // Generate and verify all the journal files before writing any of them
+    let mut journal_files = Vec::new();
+    let mut first_error: Option<CliError> = None;
+    for journal in group_by_date(entries) {
+        match generate_journal_file(args, output_dir, &journal, &settings) {
+            Ok(journal_file) => journal_files.push(journal_file),
+            Err(e) => {
+                if let Some(msg) = e.message() {
+                    error!("{}", msg);
+                }
+                first_error.get_or_insert(CliError::new(e.exit_status()));
             }
         }
-        remaining_diagnostics = diagnostics;
-        break;
+    }
+    if let Some(e) = first_error {
+        return Err(e);
+    }
+
+    create_dir_all(output_dir).map_err(|e| {
+        CliError::new(E_IOERR)
+            .with_message(format!("Failed to prepare directory {output_dir:?}: {e:?}"))
+    })?;
+    for (path, content) in journal_files {
+        write(&path, content).map_err(|e| {
+            CliError::new(E_IOERR).with_message(format!("Failed to write {path:?}: {e:?}"))
+        })?;
+        println!("Wrote {}", path.to_string_lossy());
+    }
+    Ok(())
+}
+
+/// Make the path and the content of a journal file to import, verifying that the content is
+/// read back as the imported entries without errors nor incorrect durations.
+///
+/// Other rule violations such as gaps between entries are reported but tolerated since they
+/// come from the imported data.
+fn generate_journal_file(
+    args: &Arguments,
+    output_dir: &Path,
+    journal: &ImportedJournal,
+    settings: &Settings,
+) -> Result<(PathBuf, String), CliError> {
+    let filename = format!("{}.md", journal.date.format(&settings.filename_date_format));
+    let path = output_dir.join(&filename);
+    if path.exists() && !args.overwrite {
+        return Err(CliError::new(E_USAGE).with_message(format!(
+            "Refused to overwrite {:?}; use --overwrite to overwrite it",
+            path
+        )));
     }
 
+    let content = journal.to_markdown(settings).map_err(|e| {
+        CliError::new(E_UNEXPECTED).with_message(format!("Failed to generate {filename:?}: {e:?}"))
+    })?;
+    let abs_path = absolute(output_dir).map_err(|e| {
+        CliError::new(E_IOERR).with_message(format!(
+            "Failed to make the directory {output_dir:?} absolute: {e:?}"
+        ))
+    })?;
+    let url = Url::from_file_path(abs_path.join(&filename)).map_err(|_| {
+        CliError::new(E_UNEXPECTED)
+            .with_message(format!("Failed to compose URL from path {path:?}"))
+    })?;
+
+    // Parse the content and lint the AST, reporting violations to *stderr*
+    let (ast, diagnostics) = parse_and_lint(&url, &content, settings);
+    let line_mapper = Arc::new(LineMapper::new(&content));
+    let path_str = path.to_string_lossy();
+    for diagnostic in &diagnostics {
+        report(
+            ReportFormat::OneLine,
+            &content,
+            &line_mapper,
+            Some(&path_str),
+            diagnostic,
+            io::stderr(),
+        )
+        .map_err(|e| {
+            CliError::new(E_UNEXPECTED)
+                .with_message(format!("Failed on reporting rule violations: {e:?}"))
+        })?;
+    }
+
+    // Durations are written so that they match the time ranges, so an incorrect one means that
+    // the entry is not represented correctly
+    let has_errors = diagnostics
+        .iter()
+        .any(|d| d.severity() == Severity::Error || d.rule() == &Rule::IncorrectDuration);
+    let round_trips = match &ast {
+        Some(ast) => journal.round_trips(ast).map_err(|e| {
+            CliError::new(E_UNEXPECTED).with_message(format!("Failed to collect entries: {:?}", e))
+        })?,
+        None => false,
+    };
+    if has_errors || !round_trips {
+        return Err(CliError::new(E_VIOLATIONS).with_message(format!(
+            "Generated {path_str} does not represent the imported entries correctly"
+        )));
+    }
+    Ok((path, content))
+}
+
+/// Number of problems found across all processed files.
+#[derive(Debug, Default)]
+struct Summary {
+    num_problems: usize,
+    num_errors: usize,
+    num_warnings: usize,
+    num_files_with_problems: usize,
+    num_files: usize,
+}
+
+impl Summary {
+    fn add(&mut self, severities: &[Severity]) {
+        self.num_problems += severities.len();
+        self.num_errors += severities.iter().filter(|&&s| s == Severity::Error).count();
+        self.num_warnings += severities
+            .iter()
+            .filter(|&&s| s == Severity::Warning)
+            .count();
+        if !severities.is_empty() {
+            self.num_files_with_problems += 1;
+        }
+        self.num_files += 1;
+    }
+
+    /// Write the summary unless no problem was found.
+    fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+        if self.num_problems == 0 {
+            return Ok(());
+        }
+        writeln!(
+            w,
+            "Found {} ({}, {}) in {} ({} checked).",
+            plural(self.num_problems, "problem"),
+            plural(self.num_errors, "error"),
+            plural(self.num_warnings, "warning"),
+            plural(self.num_files_with_problems, "file"),
+            plural(self.num_files, "file"),
+        )
+    }
+}
+
+fn plural(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("{n} {noun}")
+    } else {
+        format!("{n} {noun}s")
+    }
+}
+
+fn main_fix(
+    filename: &str,
+    url: &Url,
+    content: &str,
+    settings: &Settings,
+    reporter: &mut Reporter<impl Write>,
+) -> Result<Vec<Severity>, CliError> {
+    // Create a working copy of the content.
+    let mut buffer = String::with_capacity(content.len() + 128);
+    buffer.push_str(content);
+
+    // Fix the violations in the working copy
+    let (num_fixed, remaining_diagnostics) = fix_all(url, &mut buffer, settings).map_err(|e| {
+        CliError::new(E_UNEXPECTED)
+            .with_message(format!("Failed on fixing a rule violation: {e:?}"))
+    })?;
+
     // Write the content back unless nothing changed
     if 0 < num_fixed {
         let path = url
             .to_file_path()
             .expect("journalint CLI does not expect to process non-local file");
-        write(path, buffer).map_err(|e| {
-            CliError::new(exitcode::IOERR)
-                .with_message(format!("Failed on writing fixed result: {e:?}"))
+        write(path, &buffer).map_err(|e| {
+            CliError::new(E_IOERR).with_message(format!("Failed on writing fixed result: {e:?}"))
         })?;
     }
 
-    // Write remaining diagnostic report to stdout
+    // Write remaining diagnostic report in the selected format
     log::debug!("!!! {:?}", remaining_diagnostics);
-    let line_mapper = Arc::new(LineMapper::new(content)); //TODO: Stop using Arc
+    let severities = remaining_diagnostics
+        .iter()
+        .map(Diagnostic::severity)
+        .collect();
+    // The remaining diagnostics are found in the fixed content, whose lines may be reordered
+    let line_mapper = Arc::new(LineMapper::new(&buffer)); //TODO: Stop using Arc
     for diagnostic in remaining_diagnostics {
-        report(
-            ReportFormat::OneLine,
-            content,
-            &line_mapper,
-            Some(filename),
-            &diagnostic,
-            io::stdout(),
-        )
-        .map_err(|e| CliError::new(exitcode::IOERR).with_message(e.to_string()))?;
+        reporter
+            .report(&buffer, &line_mapper, Some(filename), &diagnostic)
+            .map_err(|e| CliError::new(E_IOERR).with_message(e.to_string()))?;
     }
 
-    Ok(())
+    Ok(severities)
 }
 
 fn main_export(
     filename: &str,
     url: &Url,
     content: &str,
-    export_format: ExportFormat,
+    settings: &Settings,
     extract_activity_prefixes: bool,
-) -> Result<(), CliError> {
+    filter: &EntryFilter,
+    collected: &mut CollectedEntries,
+) -> Result<Vec<Severity>, CliError> {
     // Parse the content and lint the AST unless parsing itself failed
-    let (journal, diagnostics) = parse_and_lint(url, content);
+    let (journal, diagnostics) = parse_and_lint(url, content, settings);
+
+    // Skip the journal entirely, including its diagnostics, if it is dated out of the range
+    if let Some(journal) = &journal {
+        let info = JournalInfo::collect(journal).map_err(|e| {
+            CliError::new(E_UNEXPECTED).with_message(format!("Failed to read the date: {:?}", e))
+        })?;
+        if info.date.is_some_and(|date| !filter.includes_date(date)) {
+            return Ok(Vec::new());
+        }
+    }
+    let severities = diagnostics.iter().map(Diagnostic::severity).collect();
 
     // Write simple diagnostic report to *stderr*
     let line_mapper = Arc::new(LineMapper::new(content)); //TODO: Stop using Arc
@@ -138,84 +509,38 @@ fn main_export(
         })?;
     }
 
-    // Export parsed data to stdout
+    // Collect entries to export
     if let Some(journal) = journal {
-        let mut writer = std::io::stdout();
-        export(
-            export_format,
-            extract_activity_prefixes,
-            journal,
-            &mut writer,
-        )
-        .map_err(|e| {
-            CliError::new(E_UNEXPECTED).with_message(format!("Failed to export data: {:?}", e))
-        })?;
+        let mut journal_entries = collect_entries(extract_activity_prefixes, filter, &journal)
+            .map_err(|e| {
+                CliError::new(E_UNEXPECTED)
+                    .with_message(format!("Failed to collect entries: {:?}", e))
+            })?;
+        collected.entries.append(&mut journal_entries);
+        collected.max_codes = collected.max_codes.max(Some(settings.max_codes));
     }
 
-    Ok(())
+    Ok(severities)
 }
 
 fn main_report(
     filename: &str,
     url: &Url,
     content: &str,
-    report_format: ReportFormat,
-) -> Result<(), CliError> {
+    settings: &Settings,
+    reporter: &mut Reporter<impl Write>,
+) -> Result<Vec<Severity>, CliError> {
     // Parse the content and lint the AST unless parsing itself failed
-    let (_journal, diagnostics) = parse_and_lint(url, content);
+    let (_journal, diagnostics) = parse_and_lint(url, content, settings);
+    let severities = diagnostics.iter().map(Diagnostic::severity).collect();
 
-    // Write diagnostic report to stdout
+    // Write diagnostic report
     let line_mapper = Arc::new(LineMapper::new(content)); //TODO: Stop using Arc
     for diagnostic in diagnostics {
-        report(
-            report_format,
-            content,
-            &line_mapper,
-            Some(filename),
-            &diagnostic,
-            io::stdout(),
-        )
-        .map_err(|e| CliError::new(exitcode::IOERR).with_message(e.to_string()))?;
+        reporter
+            .report(content, &line_mapper, Some(filename), &diagnostic)
+            .map_err(|e| CliError::new(E_IOERR).with_message(e.to_string()))?;
     }
 
-    Ok(())
-}
-
-fn fix_violation(
-    url: &Url,
-    journal: Option<&ast::Expr>,
-    diagnostic: &Diagnostic,
-    buffer: &mut String,
-) -> Result<bool, JournalintError> {
-    // Check if there is a default auto-fix command for the rule violation.
-    let (Some(journal), Some(command)) = (journal, get_default_autofix(diagnostic.rule())) else {
-        return Ok(false); // unavailable
-    };
-
-    // Execute the default auto-fix command.
-    let Some(text_edit) = command.execute(url, journal, diagnostic.span())? else {
-        return Ok(false);
-    };
-
-    // Apply the fix to on-memory buffer.
-    text_edit.apply(buffer);
-    Ok(true)
-}
-
-/// Get default auto-fix command for the rule violation.
-fn get_default_autofix(rule: &Rule) -> Option<impl Command> {
-    match rule {
-        Rule::ParseError => None,
-        Rule::MismatchedDates => Some(AutofixCommand::UseDateInFilename),
-        Rule::MismatchedStartTime => None,
-        Rule::MismatchedEndTime => None,
-        Rule::InvalidStartTime => None,
-        Rule::InvalidEndTime => None,
-        Rule::MissingDate => None,
-        Rule::MissingStartTime => None,
-        Rule::MissingEndTime => None,
-        Rule::TimeJumped => Some(AutofixCommand::ReplaceWithPreviousEndTime),
-        Rule::NegativeTimeRange => None,
-        Rule::IncorrectDuration => Some(AutofixCommand::RecalculateDuration),
-    }
+    Ok(severities)
 }
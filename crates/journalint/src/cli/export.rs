@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 use std::io::Write;
 use std::ops::Range;
+use std::str::FromStr;
 use std::time::Duration;
 
 use chrono::prelude::*;
@@ -8,6 +9,7 @@ use chrono::prelude::*;
 use journalint_parse::ast;
 
 use crate::errors::JournalintError;
+use crate::journal_info::split_activity_prefixes;
 
 /// Export data format.
 #[derive(Clone, Debug, clap::ValueEnum)]
@@ -17,15 +19,24 @@ pub enum ExportFormat {
 
     /// CSV with a header line.
     Csv,
+
+    /// iCalendar with an event per entry.
+    Ical,
+
+    /// Timeclock format of Ledger and hledger with a pair of clock-in and clock-out lines per
+    /// entry.
+    Timeclock,
 }
 
 #[derive(Debug, serde::Serialize)]
-struct JournalEntry {
-    start_time: DateTime<Utc>,
-    end_time: DateTime<Utc>,
-    duration: u64, // seconds
-    codes: Vec<String>,
-    activity: String,
+pub struct JournalEntry {
+    pub(super) date: NaiveDate,
+    pub(super) start_time: DateTime<Utc>,
+    pub(super) end_time: DateTime<Utc>,
+    pub(super) duration: u64, // seconds
+    pub(super) codes: Vec<String>,
+    pub(super) activity_prefixes: Vec<String>, // extracted from the activity
+    pub(super) activity: String,
 }
 
 impl JournalEntry {
@@ -34,20 +45,276 @@ impl JournalEntry {
         entry.insert("start_time".to_string(), self.start_time.to_rfc3339());
         entry.insert("end_time".to_string(), self.end_time.to_rfc3339());
         entry.insert("duration".to_string(), self.duration.to_string());
-        for (i, code) in self.codes.iter().enumerate() {
+        for (i, code) in self.codes.iter().chain(&self.activity_prefixes).enumerate() {
             let key = format!("code{}", i + 1);
             entry.insert(key, code.clone());
         }
         entry.insert("activity".to_string(), self.activity.clone());
         entry
     }
+
+    /// Make the activity as written in the journal, joining the extracted activity prefixes
+    /// back if any.
+    fn whole_activity(&self) -> String {
+        self.activity_prefixes
+            .iter()
+            .map(String::as_str)
+            .chain([self.activity.as_str()])
+            .collect::<Vec<&str>>()
+            .join(": ")
+    }
+
+    /// Make a CSV record with the columns as described in [`csv_header`].
+    fn to_csv_record(&self, num_code_columns: usize) -> Result<Vec<String>, JournalintError> {
+        if num_code_columns < self.codes.len() {
+            return Err(JournalintError::TooManyCodes {
+                count: self.codes.len(),
+                max: num_code_columns,
+            });
+        }
+        let mut record = vec![
+            self.date.format("%Y-%m-%d").to_string(),
+            self.start_time.to_rfc3339(),
+            self.end_time.to_rfc3339(),
+            self.duration.to_string(),
+        ];
+        for i in 0..num_code_columns {
+            record.push(self.codes.get(i).cloned().unwrap_or_default());
+        }
+        record.push(self.whole_activity());
+        Ok(record)
+    }
+}
+
+/// Maximum length of a content line of iCalendar in octets, excluding the line break.
+const ICAL_MAX_LINE_LENGTH: usize = 75;
+
+impl JournalEntry {
+    /// Make content lines of a `VEVENT` component.
+    ///
+    /// Times are written as "floating" ones, which are the same wall clock time in any time zone,
+    /// since journals do not record time zones.
+    fn to_ical_event(&self) -> Vec<String> {
+        let time_format = "%Y%m%dT%H%M%S";
+        let start_time = self.start_time.format(time_format);
+        let mut lines = vec![
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{start_time}@journalint"),
+            // Use the start time rather than the time of exporting to keep the output stable
+            format!("DTSTAMP:{start_time}Z"),
+            format!("DTSTART:{start_time}"),
+            format!("DTEND:{}", self.end_time.format(time_format)),
+            format!("SUMMARY:{}", escape_ical_text(&self.activity)),
+        ];
+        let categories: Vec<String> = self
+            .codes
+            .iter()
+            .chain(&self.activity_prefixes)
+            .map(|c| escape_ical_text(c))
+            .collect();
+        if !categories.is_empty() {
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+        lines.push("END:VEVENT".to_string());
+        lines
+    }
+}
+
+/// Account name of entries without codes or activity prefixes in timeclock format.
+pub(super) const TIMECLOCK_DEFAULT_ACCOUNT: &str = "unknown";
+
+impl JournalEntry {
+    /// Make clock-in and clock-out lines of timeclock format.
+    ///
+    /// The account is the codes followed by the activity prefixes separated by colons, and the
+    /// description is the whole activity. Repeating the activity prefixes in the description
+    /// tells them from the codes on importing.
+    fn to_timeclock_lines(&self) -> [String; 2] {
+        let time_format = "%Y-%m-%d %H:%M:%S";
+        let activity = self.whole_activity();
+        let (prefixes, _) = split_activity_prefixes(&activity);
+        let names = timeclock_account_names(self.codes.iter().map(String::as_str).chain(prefixes));
+        let account = if names.is_empty() {
+            TIMECLOCK_DEFAULT_ACCOUNT.to_string()
+        } else {
+            names.join(":")
+        };
+
+        // Two or more spaces separate the account from the description
+        let mut clock_in = format!("i {} {account}", self.start_time.format(time_format));
+        let description = activity.trim();
+        if !description.is_empty() {
+            clock_in.push_str("  ");
+            clock_in.push_str(description);
+        }
+        let clock_out = format!("o {}", self.end_time.format(time_format));
+        [clock_in, clock_out]
+    }
+}
+
+/// Make names of a timeclock account from codes and activity prefixes, collapsing whitespaces
+/// which would end the account and skipping empty ones.
+pub(super) fn timeclock_account_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    names
+        .into_iter()
+        .map(|name| name.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Escape special characters in a TEXT value of iCalendar.
+fn escape_ical_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write a content line of iCalendar, folding it so that each line fits in the maximum length.
+fn write_ical_line(writer: &mut impl Write, line: &str) -> std::io::Result<()> {
+    let mut len = 0;
+    for c in line.chars() {
+        if ICAL_MAX_LINE_LENGTH < len + c.len_utf8() {
+            // Continuation lines start with a space, which counts toward their lengths
+            writer.write_all(b"\r\n ")?;
+            len = 1;
+        }
+        write!(writer, "{c}")?;
+        len += c.len_utf8();
+    }
+    writer.write_all(b"\r\n")
+}
+
+/// Make the CSV header: date, start_time, end_time, duration, code1..codeN, activity.
+///
+/// The activity is written as it is in the journal, even if activity prefixes are extracted, so
+/// that the columns stay the same.
+fn csv_header(num_code_columns: usize) -> Vec<String> {
+    let mut header = vec![
+        "date".to_string(),
+        "start_time".to_string(),
+        "end_time".to_string(),
+        "duration".to_string(),
+    ];
+    for i in 0..num_code_columns {
+        header.push(format!("code{}", i + 1));
+    }
+    header.push("activity".to_string());
+    header
+}
+
+/// Pattern to match codes or activity prefixes against.
+///
+/// A pattern enclosed in slashes such as `/^AB[0-9]+$/` is a regular expression which may match
+/// a part of the text, and the others are glob patterns such as `AB*` which must match the whole
+/// text.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Glob(glob::Pattern),
+    Regex(regex::Regex),
+}
+
+impl Pattern {
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Pattern::Glob(pattern) => pattern.matches(text),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            Some(regex) => regex::Regex::new(regex)
+                .map(Pattern::Regex)
+                .map_err(|e| e.to_string()),
+            None => glob::Pattern::new(s)
+                .map(Pattern::Glob)
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Conditions which entries must meet to be exported.
+#[derive(Clone, Debug, Default)]
+pub struct EntryFilter {
+    /// Earliest date of journals to export, inclusive.
+    pub since: Option<NaiveDate>,
+
+    /// Latest date of journals to export, inclusive.
+    pub until: Option<NaiveDate>,
+
+    /// Patterns any of which some code of an entry must match, unless empty.
+    pub codes: Vec<Pattern>,
+
+    /// Patterns none of which any code of an entry may match.
+    pub excluded_codes: Vec<Pattern>,
+
+    /// Patterns any of which some activity prefix of an entry must match, unless empty.
+    pub activity_prefixes: Vec<Pattern>,
+
+    /// Patterns none of which any activity prefix of an entry may match.
+    pub excluded_activity_prefixes: Vec<Pattern>,
+
+    /// Minimum duration written in entries.
+    pub min_duration: Option<Duration>,
+}
+
+impl EntryFilter {
+    /// Check whether journals of the date are in the range of `since` and `until`.
+    pub fn includes_date(&self, date: NaiveDate) -> bool {
+        self.since.is_none_or(|since| since <= date) && self.until.is_none_or(|until| date <= until)
+    }
+
+    fn matches(
+        &self,
+        date: NaiveDate,
+        codes: &[String],
+        prefixes: &[&str],
+        duration: &Duration,
+    ) -> bool {
+        if !self.includes_date(date) {
+            return false;
+        }
+        if self.min_duration.is_some_and(|min| *duration < min) {
+            return false;
+        }
+        let codes: Vec<&str> = codes.iter().map(String::as_str).collect();
+        matches_patterns(&codes, &self.codes, &self.excluded_codes)
+            && matches_patterns(
+                prefixes,
+                &self.activity_prefixes,
+                &self.excluded_activity_prefixes,
+            )
+    }
+}
+
+/// Check that some of the words match any of the included patterns (if any), and none of them
+/// matches the excluded patterns.
+fn matches_patterns(words: &[&str], included: &[Pattern], excluded: &[Pattern]) -> bool {
+    let matches_any = |patterns: &[Pattern]| {
+        words
+            .iter()
+            .any(|word| patterns.iter().any(|p| p.matches(word)))
+    };
+    (included.is_empty() || matches_any(included)) && !matches_any(excluded)
 }
 
 struct Exporter<'a> {
     // Initialization parameters
-    fmt: ExportFormat,
-    writer: &'a mut dyn Write,
     split_activity_prefixes: bool,
+    filter: &'a EntryFilter,
 
     // Object state as a visitor
     date: Option<NaiveDate>,
@@ -56,31 +323,34 @@ struct Exporter<'a> {
     curr_duration: Option<Duration>,
     curr_codes: Vec<String>,
     curr_activity: Option<String>,
+
+    // Collected entries
+    entries: Vec<JournalEntry>,
 }
 
 impl<'a> Exporter<'a> {
     fn run(
-        fmt: ExportFormat,
         split_activity_prefixes: bool,
-        journal: ast::Expr,
-        writer: &'a mut impl Write,
-    ) -> Result<(), JournalintError> {
+        filter: &'a EntryFilter,
+        journal: &ast::Expr,
+    ) -> Result<Vec<JournalEntry>, JournalintError> {
         let mut this = Self {
-            fmt,
-            writer,
             split_activity_prefixes,
+            filter,
             date: None,
             curr_start_time: None,
             curr_end_time: None,
             curr_duration: None,
             curr_codes: Vec::new(),
             curr_activity: None,
+            entries: Vec::new(),
         };
-        ast::walk(&journal, &mut this)
+        ast::walk(journal, &mut this)?;
+        Ok(this.entries)
     }
 }
 
-impl<'a> ast::Visitor<JournalintError> for Exporter<'a> {
+impl ast::Visitor<JournalintError> for Exporter<'_> {
     fn on_visit_fm_date(
         &mut self,
         value: &NaiveDate,
@@ -142,6 +412,9 @@ impl<'a> ast::Visitor<JournalintError> for Exporter<'a> {
 
     fn on_leave_entry(&mut self, _span: &Range<usize>) -> Result<(), JournalintError> {
         // Skip exporting the entry if any of the components were invalid
+        let Some(date) = self.date else {
+            return Ok(());
+        };
         let Some(start_time) = self.curr_start_time else {
             return Ok(());
         };
@@ -155,54 +428,290 @@ impl<'a> ast::Visitor<JournalintError> for Exporter<'a> {
             return Ok(());
         };
 
-        // Concatenate prefixes of activity into codes
-        let mut codes = self.curr_codes.clone();
-        let activity_body = if self.split_activity_prefixes {
-            if let Some((body, prefixes)) = activity.split(": ").collect::<Vec<&str>>().split_last()
-            {
-                // One or more prefixes found. Extract them.
-                prefixes.iter().map(|&s| s.to_string()).for_each(|prefix| {
-                    codes.push(prefix);
-                });
-                body.to_string()
-            } else {
-                // No prefixes found. Use activity field value as activity.
-                activity.clone()
-            }
+        // Skip exporting the entry if filtered out
+        let (prefixes, body) = split_activity_prefixes(activity);
+        if !self
+            .filter
+            .matches(date, &self.curr_codes, &prefixes, &duration)
+        {
+            return Ok(());
+        }
+
+        // Separate prefixes of activity from the rest
+        let (activity_prefixes, activity_body) = if self.split_activity_prefixes {
+            (
+                prefixes.into_iter().map(str::to_string).collect(),
+                body.to_string(),
+            )
         } else {
-            activity.clone()
+            (Vec::new(), activity.clone())
         };
 
-        // Create a struct for serialization purpose
-        let entry = JournalEntry {
+        // Remember the entry until all of them are collected
+        self.entries.push(JournalEntry {
+            date,
             start_time,
             end_time,
             duration: duration.as_secs(),
-            codes,
+            codes: self.curr_codes.clone(),
+            activity_prefixes,
             activity: activity_body,
-        }
-        .to_flat_map();
-
-        // Serialize
-        let bytes = match self.fmt {
-            ExportFormat::Json => serde_json::to_vec(&entry).map_err(JournalintError::from)?,
-            ExportFormat::Csv => todo!(), // TODO: Implement
-        };
-        self.writer
-            .write_all(bytes.as_slice())
-            .map_err(JournalintError::from)?;
-        self.writer
-            .write_all("\n".as_bytes())
-            .map_err(JournalintError::from)?;
+        });
         Ok(())
     }
 }
 
+/// Collect valid entries in a journal for exporting.
+pub fn collect_entries(
+    split_activity_prefixes: bool,
+    filter: &EntryFilter,
+    journal: &ast::Expr,
+) -> Result<Vec<JournalEntry>, JournalintError> {
+    Exporter::run(split_activity_prefixes, filter, journal)
+}
+
+/// Write entries in the specified format.
+///
+/// `max_codes` is the maximum number of codes in an entry which the journals allow, used for the
+/// number of code columns of CSV.
 pub fn export(
     fmt: ExportFormat,
-    split_activity_prefixes: bool,
-    journal: ast::Expr,
+    entries: &[JournalEntry],
+    max_codes: usize,
     writer: &mut impl Write,
 ) -> Result<(), JournalintError> {
-    Exporter::run(fmt, split_activity_prefixes, journal, writer)
+    match fmt {
+        ExportFormat::Json => {
+            for entry in entries {
+                serde_json::to_writer(&mut *writer, &entry.to_flat_map())?;
+                writer.write_all("\n".as_bytes())?;
+            }
+        }
+        ExportFormat::Csv => {
+            // Make as many code columns as the parser accepts so that the columns stay the same
+            // regardless of how many codes the exported entries actually have
+            let num_code_columns = max_codes;
+            let mut csv_writer = csv::Writer::from_writer(writer);
+            csv_writer.write_record(csv_header(num_code_columns))?;
+            for entry in entries {
+                csv_writer.write_record(entry.to_csv_record(num_code_columns)?)?;
+            }
+            csv_writer.flush()?;
+        }
+        ExportFormat::Ical => {
+            write_ical_line(writer, "BEGIN:VCALENDAR")?;
+            write_ical_line(writer, "VERSION:2.0")?;
+            write_ical_line(
+                writer,
+                &format!(
+                    "PRODID:-//journalint//journalint {}//EN",
+                    env!("CARGO_PKG_VERSION")
+                ),
+            )?;
+            for entry in entries {
+                for line in entry.to_ical_event() {
+                    write_ical_line(writer, &line)?;
+                }
+            }
+            write_ical_line(writer, "END:VCALENDAR")?;
+        }
+        ExportFormat::Timeclock => {
+            for entry in entries {
+                for line in entry.to_timeclock_lines() {
+                    writeln!(writer, "{line}")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::parse::parse;
+    use journalint_parse::settings::Settings;
+    use rstest::*;
+
+    const JOURNAL: &str = concat!(
+        "---\n",
+        "date: 2006-01-02\n",
+        "start: 09:00\n",
+        "end: 24:30\n",
+        "---\n",
+        "\n",
+        "# Today\n",
+        "\n",
+        "- 09:00-10:15 ABCDEFG8 AB3 1.25 foo: bar: Developer mtg, weekly\n",
+        "- 10:15-12:00 X1 1.75 foo: Review \"journalint\" PR\n",
+        "- 12:00-23:00 0.00 foo: written duration is exported as is\n",
+        "- 23:00-24:30 1.50 Deploy\n",
+    );
+
+    fn run(fmt: ExportFormat, split_activity_prefixes: bool) -> String {
+        let settings = Settings::default();
+        let (journal, errors) = parse(JOURNAL, &settings);
+        assert_eq!(errors, []);
+        let entries = collect_entries(
+            split_activity_prefixes,
+            &EntryFilter::default(),
+            &journal.unwrap(),
+        )
+        .unwrap();
+        let mut buf = Vec::new();
+        export(fmt, &entries, settings.max_codes, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn json() {
+        insta::assert_snapshot!(run(ExportFormat::Json, false));
+    }
+
+    #[test]
+    fn csv() {
+        insta::assert_snapshot!(run(ExportFormat::Csv, false));
+    }
+
+    #[test]
+    fn csv_extracting_activity_prefixes() {
+        insta::assert_snapshot!(run(ExportFormat::Csv, true));
+    }
+
+    #[test]
+    fn filter_by_date() {
+        let (journal, _errors) = parse(JOURNAL, &Settings::default());
+        let journal = journal.unwrap();
+        let date = NaiveDate::from_ymd_opt(2006, 1, 2).unwrap();
+        let count = |since, until| {
+            let filter = EntryFilter {
+                since,
+                until,
+                ..Default::default()
+            };
+            collect_entries(false, &filter, &journal).unwrap().len()
+        };
+        assert_eq!(count(Some(date), Some(date)), 4);
+        assert_eq!(count(date.succ_opt(), None), 0);
+        assert_eq!(count(None, date.pred_opt()), 0);
+
+        let filter = EntryFilter {
+            since: date.succ_opt(),
+            ..Default::default()
+        };
+        assert!(!filter.includes_date(date));
+        assert!(filter.includes_date(date.succ_opt().unwrap()));
+    }
+
+    #[test]
+    fn ical() {
+        let output = run(ExportFormat::Ical, false);
+        assert!(output
+            .split_terminator('\n')
+            .all(|line| line.ends_with('\r')));
+        let output = output
+            .replace("\r\n", "\n")
+            .replace(env!("CARGO_PKG_VERSION"), "VERSION");
+        insta::assert_snapshot!(output);
+    }
+
+    #[test]
+    fn timeclock() {
+        insta::assert_snapshot!(run(ExportFormat::Timeclock, false));
+    }
+
+    #[test]
+    fn timeclock_extracting_activity_prefixes() {
+        // Extracted prefixes make the same account names
+        assert_eq!(
+            run(ExportFormat::Timeclock, true),
+            run(ExportFormat::Timeclock, false)
+        );
+    }
+
+    #[test]
+    fn write_ical_line() {
+        let mut buf = Vec::new();
+        let line = format!("SUMMARY:{}", "あ".repeat(30));
+        super::write_ical_line(&mut buf, &line).unwrap();
+        let output = String::from_utf8(buf).unwrap();
+        let lines: Vec<&str> = output.split("\r\n").collect();
+        assert_eq!(
+            lines,
+            vec![
+                format!("SUMMARY:{}", "あ".repeat(22)).as_str(),
+                format!(" {}", "あ".repeat(8)).as_str(),
+                ""
+            ]
+        );
+    }
+
+    /// Collect activities of the entries passing the filter.
+    fn filtered_activities(filter: &EntryFilter) -> Vec<String> {
+        let (journal, _errors) = parse(JOURNAL, &Settings::default());
+        collect_entries(false, filter, &journal.unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.activity)
+            .collect()
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<Pattern> {
+        patterns.iter().map(|p| p.parse().unwrap()).collect()
+    }
+
+    #[rstest]
+    #[case(&["AB*"], &[], vec!["foo: bar: Developer mtg, weekly"])]
+    #[case(&["/^[A-Z]+[0-9]$/"], &[], vec!["foo: bar: Developer mtg, weekly", "foo: Review \"journalint\" PR"])]
+    #[case(&[], &["X?"], vec!["foo: bar: Developer mtg, weekly", "foo: written duration is exported as is", "Deploy"])]
+    #[case(&["A*", "X*"], &["/8$/"], vec!["foo: Review \"journalint\" PR"])]
+    fn filter_by_code(
+        #[case] included: &[&str],
+        #[case] excluded: &[&str],
+        #[case] want: Vec<&str>,
+    ) {
+        let filter = EntryFilter {
+            codes: patterns(included),
+            excluded_codes: patterns(excluded),
+            ..Default::default()
+        };
+        assert_eq!(filtered_activities(&filter), want);
+    }
+
+    #[test]
+    fn filter_by_activity_prefix() {
+        let filter = EntryFilter {
+            activity_prefixes: patterns(&["foo"]),
+            excluded_activity_prefixes: patterns(&["/a/"]),
+            ..Default::default()
+        };
+        assert_eq!(
+            filtered_activities(&filter),
+            vec![
+                "foo: Review \"journalint\" PR",
+                "foo: written duration is exported as is",
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_by_min_duration() {
+        let filter = EntryFilter {
+            min_duration: Some(Duration::from_secs(90 * 60)),
+            ..Default::default()
+        };
+        assert_eq!(
+            filtered_activities(&filter),
+            vec!["foo: Review \"journalint\" PR", "Deploy"]
+        );
+    }
+
+    #[test]
+    fn pattern() {
+        assert!("/(/".parse::<Pattern>().is_err());
+        assert!("[".parse::<Pattern>().is_err());
+        assert!("/b.r/".parse::<Pattern>().unwrap().matches("foobar"));
+        assert!(!"b?r".parse::<Pattern>().unwrap().matches("foobar"));
+    }
 }
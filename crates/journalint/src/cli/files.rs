@@ -0,0 +1,135 @@
+use std::collections::HashSet;
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+
+use journalint_parse::settings::Settings;
+
+use crate::config::resolve_settings;
+use crate::errors::{CliError, E_IOERR, E_USAGE};
+use crate::journal_file::is_journal_file;
+
+/// Expand files, directories and glob patterns given on the command line into a list of files.
+///
+/// Files given explicitly are used as is. Directories are searched recursively for journal
+/// files. Arguments which do not exist as a file nor a directory are treated as glob patterns
+/// since shells on Windows do not expand them.
+///
+/// Whether a file is a journal file or not is determined by `filename-date-format` in the
+/// settings applied to the directory containing it, which is `config` if specified.
+pub(crate) fn collect_files(
+    args: &[String],
+    config: Option<&Path>,
+) -> Result<Vec<PathBuf>, CliError> {
+    let settings_for = |path: &Path| {
+        resolve_settings(config, path, &Settings::default())
+            .map_err(|e| CliError::new(E_USAGE).with_message(e.to_string()))
+    };
+
+    let mut paths = Vec::new();
+    for arg in args {
+        let path = PathBuf::from(arg);
+        if path.is_dir() {
+            collect_journal_files(&path, &settings_for, &mut paths)?;
+        } else if path.exists() {
+            paths.push(path);
+        } else {
+            let entries = glob::glob(arg).map_err(|e| {
+                CliError::new(E_USAGE).with_message(format!("Invalid glob pattern {arg:?}: {e}"))
+            })?;
+            let num_paths = paths.len();
+            for entry in entries {
+                let path = entry.map_err(|e| {
+                    CliError::new(E_IOERR).with_message(format!("Failed to expand {arg:?}: {e}"))
+                })?;
+                if path.is_dir() {
+                    collect_journal_files(&path, &settings_for, &mut paths)?;
+                } else if is_journal_file(&path, &settings_for(&path)?) {
+                    paths.push(path);
+                }
+            }
+            if paths.len() == num_paths {
+                return Err(CliError::new(E_IOERR)
+                    .with_message(format!("No journal file found for {arg:?}")));
+            }
+        }
+    }
+
+    // Remove duplicates keeping the order
+    let mut seen = HashSet::new();
+    paths.retain(|p| seen.insert(p.clone()));
+    Ok(paths)
+}
+
+/// Recursively collect journal files under a directory in lexicographic order.
+///
+/// Settings are resolved for each directory since a subdirectory may have its own configuration
+/// file with a different `filename-date-format`.
+fn collect_journal_files(
+    dir: &Path,
+    settings_for: &impl Fn(&Path) -> Result<Settings, CliError>,
+    paths: &mut Vec<PathBuf>,
+) -> Result<(), CliError> {
+    let settings = settings_for(dir)?;
+    let mut entries = read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|e| e.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| {
+            CliError::new(E_IOERR).with_message(format!("Failed to read directory {dir:?}: {e}"))
+        })?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            // Skip hidden directories such as `.git`
+            if !path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .is_some_and(|s| s.starts_with('.'))
+            {
+                collect_journal_files(&path, settings_for, paths)?;
+            }
+        } else if is_journal_file(&path, &settings) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use crate::config::CONFIG_FILENAME;
+
+    #[test]
+    fn nested_config() {
+        let root = std::env::temp_dir().join(format!("journalint-files-{}", std::process::id()));
+        let nested = root.join("2006");
+        create_dir_all(&nested).unwrap();
+        for path in [
+            root.join("2006-01-01.md"),
+            root.join("20060102.md"),
+            nested.join("2006-01-03.md"),
+            nested.join("20060104.md"),
+        ] {
+            write(path, "").unwrap();
+        }
+        write(
+            nested.join(CONFIG_FILENAME),
+            "filename-date-format = \"%Y%m%d\"\n",
+        )
+        .unwrap();
+
+        let paths = collect_files(&[root.to_string_lossy().to_string()], None).ok();
+        remove_dir_all(&root).unwrap();
+        assert_eq!(
+            paths.unwrap(),
+            vec![nested.join("20060104.md"), root.join("2006-01-01.md")]
+        );
+    }
+}
@@ -0,0 +1,510 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use chrono::prelude::*;
+
+use journalint_parse::ast;
+use journalint_parse::parse::parse;
+use journalint_parse::settings::Settings;
+
+use crate::cli::export::{
+    collect_entries, timeclock_account_names, EntryFilter, JournalEntry, TIMECLOCK_DEFAULT_ACCOUNT,
+};
+use crate::errors::JournalintError;
+use crate::formatter::format_content;
+use crate::journal_info::split_activity_prefixes;
+
+/// Maximum number of digits after the decimal point tried to write a duration exactly, which is
+/// enough for any number of minutes.
+const MAX_DURATION_DIGITS: usize = 15;
+
+/// Import data format.
+#[derive(Clone, Debug, clap::ValueEnum)]
+pub enum ImportFormat {
+    /// CSV with the same columns as `--export csv` writes.
+    Csv,
+
+    /// Timeclock format of Ledger and hledger.
+    Timeclock,
+}
+
+/// Entry read from data to import.
+///
+/// Times are truncated to minutes since journals do not record seconds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedEntry {
+    date: NaiveDate,
+    start_time: NaiveDateTime,
+    end_time: NaiveDateTime,
+    duration: Duration,
+    codes: Vec<String>,
+    activity: String,
+}
+
+impl ImportedEntry {
+    /// Check whether an entry collected from a journal is the same as this one.
+    fn matches(&self, entry: &JournalEntry) -> bool {
+        entry.date == self.date
+            && entry.start_time.naive_utc() == self.start_time
+            && entry.end_time.naive_utc() == self.end_time
+            && entry.duration == self.duration.as_secs()
+            && entry.codes == self.codes
+            && entry.activity == self.activity
+    }
+}
+
+/// Entries of a day to write in a journal file.
+#[derive(Debug)]
+pub struct ImportedJournal {
+    pub date: NaiveDate,
+    entries: Vec<ImportedEntry>,
+}
+
+impl ImportedJournal {
+    /// Make the content of the journal file, formatted in the same way as `--format`.
+    pub fn to_markdown(&self, settings: &Settings) -> Result<String, JournalintError> {
+        let start = self.entries.iter().map(|e| e.start_time).min();
+        let end = self.entries.iter().map(|e| e.end_time).max();
+        let (Some(start), Some(end)) = (start, end) else {
+            return Err(JournalintError::MissingRequiredValue {
+                name: "entries".to_string(),
+            });
+        };
+
+        let mut content = format!(
+            "---\ndate: {}\nstart: {}\nend: {}\n---\n\n",
+            self.date.format("%Y-%m-%d"),
+            self.loose_time(&start),
+            self.loose_time(&end)
+        );
+        for entry in &self.entries {
+            let mut fields = vec![format!(
+                "- {}-{}",
+                self.loose_time(&entry.start_time),
+                self.loose_time(&entry.end_time)
+            )];
+            fields.extend(entry.codes.iter().cloned());
+            fields.push(format_exact_duration(&entry.duration, settings));
+            fields.push(entry.activity.clone());
+            content.push_str(&fields.join(" "));
+            content.push('\n');
+        }
+
+        // Leave the content as it is if it cannot be parsed, which the verification reports
+        let (Some(journal), _errors) = parse(&content, settings) else {
+            return Ok(content);
+        };
+        Ok(format_content(&content, &journal, settings)?.unwrap_or(content))
+    }
+
+    /// Check whether the entries collected from the journal are the same as the imported ones.
+    pub fn round_trips(&self, journal: &ast::Expr) -> Result<bool, JournalintError> {
+        let entries = collect_entries(false, &EntryFilter::default(), journal)?;
+        Ok(entries.len() == self.entries.len()
+            && self.entries.iter().zip(&entries).all(|(a, b)| a.matches(b)))
+    }
+
+    /// Write a time as hours and minutes since the start of the date, which may be 24:00 or
+    /// later.
+    fn loose_time(&self, value: &NaiveDateTime) -> String {
+        let minutes = (*value - self.date.and_time(NaiveTime::MIN)).num_minutes();
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+/// Write a duration in hours with as few digits as possible, but not less than
+/// `duration-precision`, so that it is read back as the same value.
+///
+/// For example, 40 minutes is written as `0.6666666666667` since the linter compares durations
+/// exactly. If no such digits are found, the duration is written in `duration-precision` digits.
+fn format_exact_duration(value: &Duration, settings: &Settings) -> String {
+    let hours = value.as_secs_f64() / 3600.0;
+    let read_back =
+        |text: &str| str::parse::<f64>(text).map(|hours| Duration::from_secs_f64(hours * 3600.0));
+    (settings.duration_precision..=MAX_DURATION_DIGITS)
+        .map(|p| format!("{hours:.p$}"))
+        .find(|text| read_back(text).is_ok_and(|d| d == *value))
+        .unwrap_or_else(|| settings.format_duration(value))
+}
+
+/// Group entries by their dates in chronological order.
+pub fn group_by_date(entries: Vec<ImportedEntry>) -> Vec<ImportedJournal> {
+    let mut journals: BTreeMap<NaiveDate, Vec<ImportedEntry>> = BTreeMap::new();
+    for entry in entries {
+        journals.entry(entry.date).or_default().push(entry);
+    }
+    journals
+        .into_iter()
+        .map(|(date, mut entries)| {
+            entries.sort_by_key(|e| e.start_time);
+            ImportedJournal { date, entries }
+        })
+        .collect()
+}
+
+/// Read entries in the specified format.
+pub fn read_entries(
+    fmt: ImportFormat,
+    content: &str,
+    settings: &Settings,
+) -> Result<Vec<ImportedEntry>, JournalintError> {
+    match fmt {
+        ImportFormat::Csv => read_csv(content),
+        ImportFormat::Timeclock => read_timeclock(content, settings),
+    }
+}
+
+fn invalid(line: u64, reason: impl ToString) -> JournalintError {
+    JournalintError::InvalidImportData {
+        line,
+        reason: reason.to_string(),
+    }
+}
+
+fn truncate_seconds(value: NaiveDateTime) -> NaiveDateTime {
+    value
+        .with_second(0)
+        .and_then(|v| v.with_nanosecond(0))
+        .unwrap_or(value)
+}
+
+/// Read entries from CSV with the columns described in `csv_header` of the export.
+fn read_csv(content: &str) -> Result<Vec<ImportedEntry>, JournalintError> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| invalid(1, format!("missing column `{name}`")))
+    };
+    let date_column = column("date")?;
+    let start_time_column = column("start_time")?;
+    let end_time_column = column("end_time")?;
+    let duration_column = column("duration")?;
+    let activity_column = column("activity")?;
+    let mut code_columns: Vec<(usize, usize)> = headers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, h)| Some((str::parse::<usize>(h.strip_prefix("code")?).ok()?, i)))
+        .collect();
+    code_columns.sort();
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        let field = |i: usize| record.get(i).unwrap_or_default();
+        let date = NaiveDate::parse_from_str(field(date_column), "%Y-%m-%d")
+            .map_err(|e| invalid(line, format!("invalid date: {e}")))?;
+        let time = |i: usize| {
+            DateTime::parse_from_rfc3339(field(i))
+                .map(|t| truncate_seconds(t.naive_local()))
+                .map_err(|e| invalid(line, format!("invalid time: {e}")))
+        };
+        let (start_time, end_time) = (time(start_time_column)?, time(end_time_column)?);
+        if start_time.date() < date {
+            return Err(invalid(line, "start time precedes the date"));
+        }
+        let duration = str::parse::<u64>(field(duration_column))
+            .map_err(|e| invalid(line, format!("invalid duration: {e}")))?;
+        entries.push(ImportedEntry {
+            date,
+            start_time,
+            end_time: end_time.max(start_time),
+            duration: Duration::from_secs(duration),
+            codes: code_columns
+                .iter()
+                .map(|(_, i)| field(*i).to_string())
+                .filter(|code| !code.is_empty())
+                .collect(),
+            activity: field(activity_column).to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Read entries from pairs of clock-in and clock-out lines of timeclock format.
+///
+/// See `split_account` for how an account and a description become codes and an activity.
+fn read_timeclock(
+    content: &str,
+    settings: &Settings,
+) -> Result<Vec<ImportedEntry>, JournalintError> {
+    let mut entries = Vec::new();
+    let mut clock_in: Option<(u64, NaiveDateTime, &str)> = None;
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i as u64 + 1;
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with([';', '#', '*']) {
+            continue;
+        }
+
+        // Split the line into the code, the date, the time and the rest
+        let mut fields = line.splitn(4, ' ').filter(|s| !s.is_empty());
+        let (Some(code), Some(date), Some(time)) = (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid(line_number, "expected a code, a date and a time"));
+        };
+        let rest = fields.next().unwrap_or_default().trim_start();
+        let date = NaiveDate::parse_from_str(&date.replace('/', "-"), "%Y-%m-%d")
+            .map_err(|e| invalid(line_number, format!("invalid date: {e}")))?;
+        let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            .map_err(|e| invalid(line_number, format!("invalid time: {e}")))?;
+        let time = truncate_seconds(date.and_time(time));
+
+        match (code, clock_in.take()) {
+            ("i", None) => clock_in = Some((line_number, time, rest)),
+            ("i", Some(_)) => return Err(invalid(line_number, "clocked in twice")),
+            ("o", Some((_, start_time, account_and_description))) => {
+                if time < start_time {
+                    return Err(invalid(line_number, "clocked out before clocking in"));
+                }
+                let (codes, activity) = split_account(account_and_description, settings);
+                entries.push(ImportedEntry {
+                    date: start_time.date(),
+                    start_time,
+                    end_time: time,
+                    duration: (time - start_time).to_std().unwrap_or_default(),
+                    codes,
+                    activity,
+                });
+            }
+            ("o", None) => return Err(invalid(line_number, "clocked out without clocking in")),
+            _ => return Err(invalid(line_number, format!("unsupported code `{code}`"))),
+        }
+    }
+    if let Some((line_number, _, _)) = clock_in {
+        return Err(invalid(line_number, "clocked in without clocking out"));
+    }
+    Ok(entries)
+}
+
+/// Split an account and a description into codes and an activity.
+///
+/// If the account ends with the activity prefixes of the description as `--export timeclock`
+/// writes, the rest of the account are the codes and the description is the activity. Otherwise
+/// components of the account become codes as long as they look like codes, up to `max-codes`,
+/// and the rest become activity prefixes preceding the description.
+fn split_account(account_and_description: &str, settings: &Settings) -> (Vec<String>, String) {
+    // Two or more spaces or a tab separate the account from the description
+    let (account, description) = match account_and_description.find(['\t']) {
+        Some(i) => account_and_description.split_at(i),
+        None => account_and_description
+            .split_once("  ")
+            .unwrap_or((account_and_description, "")),
+    };
+    let (account, description) = (account.trim(), description.trim());
+
+    let mut names: Vec<&str> = account
+        .split(':')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect();
+    if account == TIMECLOCK_DEFAULT_ACCOUNT {
+        names.clear();
+    }
+    let is_code = |name: &str| {
+        name.chars().count() <= settings.max_code_length
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+
+    // Find the activity prefixes repeated in the account
+    let (prefixes, _) = split_activity_prefixes(description);
+    let prefix_names = timeclock_account_names(prefixes);
+    let prefix_names: Vec<&str> = prefix_names.iter().flat_map(|n| n.split(':')).collect();
+    if let Some(codes) = names
+        .strip_suffix(prefix_names.as_slice())
+        .filter(|codes| codes.len() <= settings.max_codes && codes.iter().all(|c| is_code(c)))
+    {
+        let codes = codes.iter().map(|c| c.to_string()).collect();
+        return (codes, description.to_string());
+    }
+
+    let num_codes = names
+        .iter()
+        .take(settings.max_codes)
+        .take_while(|name| is_code(name))
+        .count();
+    let codes = names.drain(..num_codes).map(str::to_string).collect();
+    if !description.is_empty() {
+        names.push(description);
+    }
+    (codes, names.join(": "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::lint::parse_and_lint;
+    use lsp_types::Url;
+
+    use crate::cli::export::{export, ExportFormat};
+
+    const JOURNAL: &str = concat!(
+        "---\n",
+        "date: 2006-01-02\n",
+        "start: 09:00\n",
+        "end: 24:30\n",
+        "---\n",
+        "\n",
+        "- 09:00-10:15 ABCDEFG8 AB3 1.25 foo: bar: Developer mtg, weekly\n",
+        "- 10:15-12:00 X1 1.75 foo: Review \"journalint\" PR\n",
+        "- 12:00-12:10 0.1666666666667 foo: short one\n",
+        "- 12:10-24:30 12.33 Deploy\n",
+    );
+
+    #[rstest::rstest]
+    #[case(false)]
+    #[case(true)]
+    fn csv(#[case] split_activity_prefixes: bool) {
+        let settings = Settings::default();
+        let (journal, errors) = parse(JOURNAL, &settings);
+        assert_eq!(errors, []);
+        let entries = collect_entries(
+            split_activity_prefixes,
+            &EntryFilter::default(),
+            &journal.unwrap(),
+        )
+        .unwrap();
+        let mut buf = Vec::new();
+        export(ExportFormat::Csv, &entries, settings.max_codes, &mut buf).unwrap();
+
+        let content = String::from_utf8(buf).unwrap();
+        let entries = read_entries(ImportFormat::Csv, &content, &settings).unwrap();
+        let journals = group_by_date(entries);
+        assert_eq!(journals.len(), 1);
+        assert_eq!(journals[0].to_markdown(&settings).unwrap(), JOURNAL);
+    }
+
+    #[test]
+    fn timeclock() {
+        let settings = Settings::default();
+        let content = concat!(
+            "; Durations are computed from the time ranges\n",
+            "i 2006-01-02 09:00:00 ABCDEFG8:AB3:foo:bar  Developer mtg, weekly\n",
+            "o 2006-01-02 10:15:00\n",
+            "i 2006-01-02 12:00:00 unknown  short one\n",
+            "o 2006-01-02 12:10:00\n",
+            "i 2006-01-02 23:00:00 X1\n",
+            "o 2006-01-03 00:30:00\n",
+            "i 2006-01-03 09:00:00 X1  next day\n",
+            "o 2006-01-03 09:30:00\n",
+        );
+        let entries = read_entries(ImportFormat::Timeclock, content, &settings).unwrap();
+        let markdowns: Vec<String> = group_by_date(entries)
+            .iter()
+            .map(|journal| journal.to_markdown(&settings).unwrap())
+            .collect();
+        assert_eq!(
+            markdowns,
+            vec![
+                concat!(
+                    "---\n",
+                    "date: 2006-01-02\n",
+                    "start: 09:00\n",
+                    "end: 24:30\n",
+                    "---\n",
+                    "\n",
+                    "- 09:00-10:15 ABCDEFG8 AB3 1.25 foo: bar: Developer mtg, weekly\n",
+                    "- 12:00-12:10 0.1666666666667 short one\n",
+                    "- 23:00-24:30 X1 1.50\n",
+                ),
+                concat!(
+                    "---\n",
+                    "date: 2006-01-03\n",
+                    "start: 09:00\n",
+                    "end: 09:30\n",
+                    "---\n",
+                    "\n",
+                    "- 09:00-09:30 X1 0.50 next day\n",
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        let settings = Settings::default();
+        let content = concat!(
+            "i 2006/01/02 09:00:30 ABC:X1:foo  bar\n",
+            "o 2006/01/02 10:00:00\n",
+        );
+        let entries = read_entries(ImportFormat::Timeclock, content, &settings).unwrap();
+        let journals = group_by_date(entries);
+        let markdown = journals[0].to_markdown(&settings).unwrap();
+        assert!(markdown.ends_with("- 09:00-10:00 ABC X1 1.00 foo: bar\n"));
+
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (journal, diagnostics) = parse_and_lint(&url, &markdown, &settings);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(journals[0].round_trips(&journal.unwrap()).unwrap());
+    }
+
+    #[test]
+    fn timeclock_round_trips() {
+        let settings = Settings::default();
+        let content = concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 09:00\n",
+            "end: 12:40\n",
+            "---\n",
+            "\n",
+            "- 09:00-10:20 AB 1.3333333333333 foo: bar baz\n",
+            "- 10:20-11:00 0.6666666666667 AB: not a code\n",
+            "- 11:00-12:00 X1 Y-2 1.00 foo: x:y: z\n",
+            "- 12:00-12:40 0.6666666666667 Deploy\n",
+        );
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (journal, diagnostics) = parse_and_lint(&url, content, &settings);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        let entries = collect_entries(false, &EntryFilter::default(), &journal.unwrap()).unwrap();
+        let mut buf = Vec::new();
+        export(
+            ExportFormat::Timeclock,
+            &entries,
+            settings.max_codes,
+            &mut buf,
+        )
+        .unwrap();
+
+        let timeclock = String::from_utf8(buf).unwrap();
+        let entries = read_entries(ImportFormat::Timeclock, &timeclock, &settings).unwrap();
+        let journals = group_by_date(entries);
+        assert_eq!(journals.len(), 1);
+        assert_eq!(journals[0].to_markdown(&settings).unwrap(), content);
+    }
+
+    #[rstest::rstest]
+    #[case("ABC:X1:foo:bar  baz", vec!["ABC", "X1"], "foo: bar: baz")]
+    #[case("AB:foo  foo: bar", vec!["AB"], "foo: bar")]
+    #[case("AB  AB: bar", vec![], "AB: bar")]
+    #[case("ABC:foo bar:X1\tbaz", vec!["ABC"], "foo bar: X1: baz")]
+    #[case("unknown  baz", vec![], "baz")]
+    #[case("foo", vec!["foo"], "")]
+    fn split_account(#[case] input: &str, #[case] codes: Vec<&str>, #[case] activity: &str) {
+        assert_eq!(
+            super::split_account(input, &Settings::default()),
+            (
+                codes.into_iter().map(str::to_string).collect(),
+                activity.to_string()
+            )
+        );
+    }
+
+    #[rstest::rstest]
+    #[case("o 2006-01-02 10:00\n", 1)]
+    #[case("i 2006-01-02 09:00 A\ni 2006-01-02 10:00 B\n", 2)]
+    #[case("i 2006-01-02 09:00 A\n", 1)]
+    #[case("; comment\ni 2006-01-02 9:xx A\n", 2)]
+    #[case("i 2006-01-02 09:00 A\nb 2006-01-02 10:00\n", 2)]
+    fn read_timeclock_error(#[case] content: &str, #[case] want: u64) {
+        match read_timeclock(content, &Settings::default()) {
+            Err(JournalintError::InvalidImportData { line, .. }) => assert_eq!(line, want),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+}
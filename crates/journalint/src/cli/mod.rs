@@ -1,7 +1,10 @@
 mod arg;
 mod cli_main;
 mod export;
+mod files;
+mod import;
 mod report;
+mod summary;
 
 pub(crate) use arg::Arguments;
 pub(crate) use cli_main::main;
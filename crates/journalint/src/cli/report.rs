@@ -3,21 +3,95 @@ use std::sync::Arc;
 
 use ariadne::{Color, Label, Report, ReportKind, Source};
 use clap::ValueEnum;
-use journalint_parse::diagnostic::Diagnostic;
+use journalint_parse::diagnostic::{Diagnostic, Severity};
+use serde_json::{json, Value};
 
 use crate::line_mapper::LineMapper;
 
 // Format of rule violation report.
-#[derive(Clone, Copy, Debug, ValueEnum)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
 pub enum ReportFormat {
     // Report rule violations using annotated source text.
     Fancy,
 
     // Report rule violations in one-line message format.
     OneLine,
+
+    // Report rule violations in JSON Lines format.
+    Json,
+
+    // Report rule violations in a SARIF 2.1.0 document.
+    Sarif,
+
+    // Report rule violations as workflow commands of GitHub Actions.
+    Github,
+}
+
+impl ReportFormat {
+    /// Whether the format is meant to be read by humans, not by programs.
+    pub fn is_human_readable(&self) -> bool {
+        matches!(self, ReportFormat::Fancy | ReportFormat::OneLine)
+    }
+}
+
+/// Writer of diagnostic reports spanning files.
+///
+/// Reports are written as soon as diagnostics are given except SARIF, which is a single document
+/// written on `finish`.
+pub struct Reporter<W: Write> {
+    format: ReportFormat,
+    w: W,
+    sarif_results: Vec<Value>,
 }
 
-/// Write a human readable report of a diagnostic
+impl<W: Write> Reporter<W> {
+    pub fn new(format: ReportFormat, w: W) -> Self {
+        Self {
+            format,
+            w,
+            sarif_results: Vec::new(),
+        }
+    }
+
+    pub fn report(
+        &mut self,
+        content: &str,
+        line_mapper: &Arc<LineMapper>,
+        filename: Option<&str>,
+        diagnostic: &Diagnostic,
+    ) -> std::io::Result<()> {
+        if self.format == ReportFormat::Sarif {
+            let filename = filename.unwrap_or(STDIN_SOURCE_NAME);
+            self.sarif_results
+                .push(sarif_result(line_mapper, filename, diagnostic));
+            return Ok(());
+        }
+        report(
+            self.format,
+            content,
+            line_mapper,
+            filename,
+            diagnostic,
+            &mut self.w,
+        )
+    }
+
+    /// Write the reports held until all the files are processed.
+    pub fn finish(mut self) -> std::io::Result<()> {
+        if self.format == ReportFormat::Sarif {
+            serde_json::to_writer_pretty(&mut self.w, &sarif_log(self.sarif_results))?;
+            writeln!(self.w)?;
+        }
+        self.w.flush()
+    }
+}
+
+const STDIN_SOURCE_NAME: &str = "<STDIN>";
+
+/// Write a report of a diagnostic.
+///
+/// SARIF is not supported by this function since it cannot be written per diagnostic, so an error
+/// of `Unsupported` kind is returned for it; use `Reporter` instead.
 #[warn(unused_results)]
 pub fn report<W: Write>(
     format: ReportFormat,
@@ -27,18 +101,24 @@ pub fn report<W: Write>(
     diagnostic: &Diagnostic,
     mut w: W,
 ) -> std::io::Result<()> {
-    let stdin_source_name = "<STDIN>".to_string();
-    let filename = filename.unwrap_or(&stdin_source_name);
+    let filename = filename.unwrap_or(STDIN_SOURCE_NAME);
     let message = diagnostic.message();
+    let color = severity_color(diagnostic.severity());
     match format {
         ReportFormat::Fancy => {
             let start = diagnostic.span().start;
             let end = diagnostic.span().end;
-            Report::build(ReportKind::Error, filename, start)
+            let kind = match diagnostic.severity() {
+                Severity::Hint => ReportKind::Advice,
+                Severity::Info => ReportKind::Custom("Info", color),
+                Severity::Warning => ReportKind::Warning,
+                Severity::Error => ReportKind::Error,
+            };
+            Report::build(kind, filename, start)
                 .with_message(message)
                 .with_label(
                     Label::new((filename, start..end))
-                        .with_color(Color::Red)
+                        .with_color(color)
                         .with_message(message),
                 )
                 .finish()
@@ -53,10 +133,277 @@ pub fn report<W: Write>(
                 Color::White.paint(filename).bold(),
                 start.line + 1,
                 start.character + 1,
-                Color::Red.paint(diagnostic.rule()),
+                color.paint(diagnostic.rule()),
                 diagnostic.message()
             )?;
         }
+        ReportFormat::Json => {
+            serde_json::to_writer(&mut w, &json_diagnostic(line_mapper, filename, diagnostic))?;
+            writeln!(w)?;
+        }
+        ReportFormat::Sarif => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "SARIF report must be written by Reporter",
+            ));
+        }
+        ReportFormat::Github => {
+            let (start, end) = one_based_range(line_mapper, diagnostic.span());
+            let command = match diagnostic.severity() {
+                Severity::Hint | Severity::Info => "notice",
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            writeln!(
+                w,
+                "::{command} file={},line={},col={},endLine={},endColumn={},title={}::{}",
+                escape_github_property(filename),
+                start.0,
+                start.1,
+                end.0,
+                end.1,
+                escape_github_property(diagnostic.rule().as_str()),
+                escape_github_data(message),
+            )?;
+        }
     };
     Ok(())
 }
+
+/// Get the color to highlight a diagnostic of the severity.
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Hint => Color::Green,
+        Severity::Info => Color::Blue,
+        Severity::Warning => Color::Yellow,
+        Severity::Error => Color::Red,
+    }
+}
+
+/// Convert a span into 1-based (line, column) pairs of its start and end.
+fn one_based_range(
+    line_mapper: &LineMapper,
+    span: &std::ops::Range<usize>,
+) -> ((u32, u32), (u32, u32)) {
+    let start = line_mapper.position_from_offset(span.start);
+    let end = line_mapper.position_from_offset(span.end);
+    (
+        (start.line + 1, start.character + 1),
+        (end.line + 1, end.character + 1),
+    )
+}
+
+fn json_range(line_mapper: &LineMapper, span: &std::ops::Range<usize>) -> Value {
+    let (start, end) = one_based_range(line_mapper, span);
+    json!({
+        "start": { "line": start.0, "column": start.1 },
+        "end": { "line": end.0, "column": end.1 },
+    })
+}
+
+fn json_diagnostic(line_mapper: &LineMapper, filename: &str, diagnostic: &Diagnostic) -> Value {
+    let related_information: Vec<Value> = diagnostic
+        .related_information()
+        .unwrap_or_default()
+        .iter()
+        .map(|info| {
+            json!({
+                "uri": info.uri().as_str(),
+                "range": json_range(line_mapper, info.range()),
+                "message": info.message(),
+            })
+        })
+        .collect();
+    json!({
+        "rule": diagnostic.rule().as_str(),
+        "severity": diagnostic.severity().as_str(),
+        "file": filename,
+        "range": json_range(line_mapper, diagnostic.span()),
+        "message": diagnostic.message(),
+        "relatedInformation": related_information,
+    })
+}
+
+fn sarif_region(line_mapper: &LineMapper, span: &std::ops::Range<usize>) -> Value {
+    let (start, end) = one_based_range(line_mapper, span);
+    json!({
+        "startLine": start.0,
+        "startColumn": start.1,
+        "endLine": end.0,
+        "endColumn": end.1,
+    })
+}
+
+fn sarif_result(line_mapper: &LineMapper, filename: &str, diagnostic: &Diagnostic) -> Value {
+    let level = match diagnostic.severity() {
+        Severity::Hint | Severity::Info => "note",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    };
+    let related_locations: Vec<Value> = diagnostic
+        .related_information()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(i, info)| {
+            json!({
+                "id": i,
+                "physicalLocation": {
+                    "artifactLocation": { "uri": info.uri().as_str() },
+                    "region": sarif_region(line_mapper, info.range()),
+                },
+                "message": { "text": info.message() },
+            })
+        })
+        .collect();
+    json!({
+        "ruleId": diagnostic.rule().as_str(),
+        "level": level,
+        "message": { "text": diagnostic.message() },
+        "locations": [{
+            "physicalLocation": {
+                // SARIF requires URI references, which use slashes as path separators
+                "artifactLocation": { "uri": filename.replace('\\', "/") },
+                "region": sarif_region(line_mapper, diagnostic.span()),
+            },
+        }],
+        "relatedLocations": related_locations,
+    })
+}
+
+fn sarif_log(results: Vec<Value>) -> Value {
+    let mut rule_ids: Vec<&str> = results
+        .iter()
+        .filter_map(|r| r["ruleId"].as_str())
+        .collect();
+    rule_ids.sort();
+    rule_ids.dedup();
+    let rules: Vec<Value> = rule_ids.iter().map(|id| json!({ "id": id })).collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/sgryjp/journalint",
+                    "rules": rules,
+                },
+            },
+            // Columns are counted in characters, not in UTF-16 code units (the default)
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }],
+    })
+}
+
+/// Escape a string to be a message of a GitHub Actions workflow command.
+fn escape_github_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a string to be a property value of a GitHub Actions workflow command.
+fn escape_github_property(s: &str) -> String {
+    escape_github_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::lint::parse_and_lint;
+    use journalint_parse::settings::Settings;
+    use lsp_types::Url;
+
+    const CONTENT: &str = concat!(
+        "---\n",
+        "date: 2006-01-02\n",
+        "start: 09:00\n",
+        "end: 12:00\n",
+        "---\n",
+        "- 09:00-10:00 2.00 foo\n",
+        "- 11:00-12:00 1.00 bar\n",
+    );
+
+    fn report_all(format: ReportFormat) -> String {
+        report_all_in(format, CONTENT)
+    }
+
+    fn report_all_in(format: ReportFormat, content: &str) -> String {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (_journal, diagnostics) = parse_and_lint(&url, content, &Settings::default());
+        let line_mapper = Arc::new(LineMapper::new(content));
+        let mut buffer = Vec::new();
+        let mut reporter = Reporter::new(format, &mut buffer);
+        for diagnostic in &diagnostics {
+            reporter
+                .report(content, &line_mapper, Some("2006-01-02.md"), diagnostic)
+                .unwrap();
+        }
+        reporter.finish().unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn json() {
+        insta::assert_snapshot!(report_all(ReportFormat::Json));
+    }
+
+    #[test]
+    fn sarif() {
+        let version = env!("CARGO_PKG_VERSION");
+        insta::assert_snapshot!(report_all(ReportFormat::Sarif).replace(version, "[version]"));
+    }
+
+    #[test]
+    fn sarif_per_diagnostic() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (_journal, diagnostics) = parse_and_lint(&url, CONTENT, &Settings::default());
+        let line_mapper = Arc::new(LineMapper::new(CONTENT));
+        let result = report(
+            ReportFormat::Sarif,
+            CONTENT,
+            &line_mapper,
+            None,
+            &diagnostics[0],
+            Vec::new(),
+        );
+        assert_eq!(
+            result.map_err(|e| e.kind()),
+            Err(std::io::ErrorKind::Unsupported)
+        );
+    }
+
+    #[test]
+    fn github() {
+        insta::assert_snapshot!(report_all(ReportFormat::Github));
+    }
+
+    #[test]
+    fn fancy_with_non_ascii_activities() {
+        let content = concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 09:00\n",
+            "end: 11:00\n",
+            "---\n",
+            "# 作業ログ\n",
+            "- 09:00-10:00 1.00 設計: レビュー\n",
+            "- 10:00-11:00 2.00 実装\n",
+        );
+        insta::assert_snapshot!(report_all_in(ReportFormat::Fancy, content));
+    }
+
+    #[test]
+    fn escape_github_property() {
+        assert_eq!(
+            super::escape_github_property("a:b,c%d\ne"),
+            "a%3Ab%2Cc%25d%0Ae"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::time::Duration;
+
+use chrono::Datelike;
+
+use journalint_parse::settings::Settings;
+
+use crate::cli::export::JournalEntry;
+use crate::errors::JournalintError;
+use crate::journal_info::split_activity_prefixes;
+
+/// Summary data format.
+#[derive(Clone, Debug, clap::ValueEnum)]
+pub enum SummaryFormat {
+    /// Plain text tables.
+    Table,
+
+    /// A JSON object.
+    Json,
+}
+
+/// Total durations of entries in seconds, grouped in several ways.
+///
+/// An entry with multiple codes (or activity prefixes) counts toward each of them, so the totals
+/// per code do not add up to `total` in general.
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct Totals {
+    total: u64,
+    codes: BTreeMap<String, u64>,
+    activity_prefixes: BTreeMap<String, u64>,
+    days: BTreeMap<String, u64>,
+    weeks: BTreeMap<String, u64>,
+    months: BTreeMap<String, u64>,
+}
+
+impl Totals {
+    fn add(&mut self, entry: &JournalEntry) {
+        let duration = entry.duration;
+        self.total += duration;
+
+        let codes: BTreeSet<&str> = entry.codes.iter().map(String::as_str).collect();
+        for code in codes {
+            *self.codes.entry(code.to_string()).or_default() += duration;
+        }
+        let (prefixes, _) = split_activity_prefixes(&entry.activity);
+        let prefixes: BTreeSet<&str> = entry
+            .activity_prefixes
+            .iter()
+            .map(String::as_str)
+            .chain(prefixes)
+            .collect();
+        for prefix in prefixes {
+            *self
+                .activity_prefixes
+                .entry(prefix.to_string())
+                .or_default() += duration;
+        }
+
+        let date = entry.date;
+        let week = date.iso_week();
+        *self
+            .days
+            .entry(date.format("%Y-%m-%d").to_string())
+            .or_default() += duration;
+        *self
+            .weeks
+            .entry(format!("{}-W{:02}", week.year(), week.week()))
+            .or_default() += duration;
+        *self
+            .months
+            .entry(date.format("%Y-%m").to_string())
+            .or_default() += duration;
+    }
+
+    fn write_table(&self, settings: &Settings, writer: &mut impl Write) -> std::io::Result<()> {
+        let sections = [
+            ("Code", &self.codes),
+            ("Activity prefix", &self.activity_prefixes),
+            ("Day", &self.days),
+            ("Week", &self.weeks),
+            ("Month", &self.months),
+        ];
+        let hours = |seconds: u64| settings.format_duration(&Duration::from_secs(seconds));
+        for (title, totals) in sections {
+            if totals.is_empty() {
+                continue;
+            }
+            let name_width = totals
+                .keys()
+                .map(|name| name.chars().count())
+                .fold(title.len(), usize::max);
+            let hours_width = totals
+                .values()
+                .map(|seconds| hours(*seconds).len())
+                .fold("Hours".len(), usize::max);
+            writeln!(writer, "{title:name_width$}  {:>hours_width$}", "Hours")?;
+            for (name, seconds) in totals {
+                writeln!(
+                    writer,
+                    "{name:name_width$}  {:>hours_width$}",
+                    hours(*seconds)
+                )?;
+            }
+            writeln!(writer)?;
+        }
+        writeln!(writer, "Total: {} hours", hours(self.total))
+    }
+}
+
+/// Sum up durations of entries.
+pub fn summarize(entries: &[JournalEntry]) -> Totals {
+    let mut totals = Totals::default();
+    for entry in entries {
+        totals.add(entry);
+    }
+    totals
+}
+
+/// Write totals in the specified format.
+pub fn write_summary(
+    fmt: SummaryFormat,
+    totals: &Totals,
+    settings: &Settings,
+    writer: &mut impl Write,
+) -> Result<(), JournalintError> {
+    match fmt {
+        SummaryFormat::Table => totals.write_table(settings, writer)?,
+        SummaryFormat::Json => {
+            serde_json::to_writer(&mut *writer, totals)?;
+            writer.write_all("\n".as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::parse::parse;
+
+    use crate::cli::export::{collect_entries, EntryFilter};
+
+    const JOURNALS: [&str; 2] = [
+        concat!(
+            "---\n",
+            "date: 2006-01-01\n",
+            "start: 09:00\n",
+            "end: 12:00\n",
+            "---\n",
+            "- 09:00-10:15 ABC 1.25 foo: bar: Developer mtg\n",
+            "- 10:15-12:00 ABC X1 1.75 foo: Review\n",
+        ),
+        concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 23:00\n",
+            "end: 24:30\n",
+            "---\n",
+            "- 23:00-24:30 1.50 Deploy\n",
+        ),
+    ];
+
+    fn run(fmt: SummaryFormat) -> String {
+        let settings = Settings::default();
+        let mut entries = Vec::new();
+        for content in JOURNALS {
+            let (journal, errors) = parse(content, &settings);
+            assert_eq!(errors, []);
+            entries.append(
+                &mut collect_entries(false, &EntryFilter::default(), &journal.unwrap()).unwrap(),
+            );
+        }
+        let mut buf = Vec::new();
+        write_summary(fmt, &summarize(&entries), &settings, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn table() {
+        insta::assert_snapshot!(run(SummaryFormat::Table));
+    }
+
+    #[test]
+    fn json() {
+        insta::assert_snapshot!(run(SummaryFormat::Json));
+    }
+}
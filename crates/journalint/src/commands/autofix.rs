@@ -6,19 +6,24 @@ use strum::EnumIter;
 
 use journalint_parse::ast::Expr;
 use journalint_parse::rule::Rule;
+use journalint_parse::settings::Settings;
 
 use crate::commands::Command;
 use crate::errors::JournalintError;
 use crate::text_edit::TextEdit;
 
-use super::{recalculate_duration, replace_with_previous_end_time, use_date_in_filename_visitor};
+use super::{
+    recalculate_duration, replace_with_previous_end_time, sort_entries,
+    use_date_in_filename_visitor,
+};
 
 /// Auto-fix command.
-#[derive(Debug, EnumIter)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
 pub enum AutofixCommand {
     RecalculateDuration,
     ReplaceWithPreviousEndTime,
     UseDateInFilename,
+    SortEntries,
 }
 
 impl Command for AutofixCommand {
@@ -31,6 +36,7 @@ impl Command for AutofixCommand {
                 "Replace with the previous entry's end time"
             }
             AutofixCommand::UseDateInFilename => "Use date embedded in the filename",
+            AutofixCommand::SortEntries => "Sort entries in chronological order",
         }
     }
 
@@ -39,6 +45,7 @@ impl Command for AutofixCommand {
             AutofixCommand::RecalculateDuration => "journalint.recalculateDuration",
             AutofixCommand::ReplaceWithPreviousEndTime => "journalint.replaceWithPreviousEndTime",
             AutofixCommand::UseDateInFilename => "journalint.useDateInFilename",
+            AutofixCommand::SortEntries => "journalint.sortEntries",
         }
     }
 
@@ -48,6 +55,7 @@ impl Command for AutofixCommand {
             AutofixCommand::RecalculateDuration => *rule == Rule::IncorrectDuration,
             AutofixCommand::ReplaceWithPreviousEndTime => *rule == Rule::TimeJumped,
             AutofixCommand::UseDateInFilename => *rule == Rule::MismatchedDates,
+            AutofixCommand::SortEntries => *rule == Rule::UnsortedEntries,
         }
     }
 
@@ -56,24 +64,29 @@ impl Command for AutofixCommand {
     /// # Arguments
     ///
     /// * `url` - URL of the document
+    /// * `content` - Content of the document
     /// * `ast_root` - AST of the document
     /// * `selection` - Span of the selection at the time this command was invoked.
+    /// * `settings` - Settings applied to the document
     fn execute(
         &self,
         url: &Url,
+        content: &str,
         ast_root: &Expr,
         selection: &Range<usize>,
+        settings: &Settings,
     ) -> Result<Option<TextEdit>, JournalintError> {
         match self {
             AutofixCommand::RecalculateDuration => {
-                recalculate_duration::execute(url, ast_root, selection)
+                recalculate_duration::execute(url, ast_root, selection, settings)
             }
             AutofixCommand::ReplaceWithPreviousEndTime => {
                 replace_with_previous_end_time::execute(url, ast_root, selection)
             }
             AutofixCommand::UseDateInFilename => {
-                use_date_in_filename_visitor::execute(url, ast_root)
+                use_date_in_filename_visitor::execute(url, ast_root, settings)
             }
+            AutofixCommand::SortEntries => sort_entries::execute(url, content, ast_root),
         }
     }
 }
@@ -0,0 +1,148 @@
+use lsp_types::Url;
+
+use journalint_parse::ast::Expr;
+use journalint_parse::diagnostic::Diagnostic;
+use journalint_parse::lint::parse_and_lint;
+use journalint_parse::rule::Rule;
+use journalint_parse::settings::Settings;
+
+use crate::errors::JournalintError;
+
+use super::{AutofixCommand, Command};
+
+/// Maximum number of fixes applied to a buffer, which guards against fixes undoing each other.
+const MAX_FIXES: usize = 1000;
+
+/// Fix rule violations in the buffer as many as possible.
+///
+/// This repeatedly executes parse, lint, and fix until no fix is done, and returns the number of
+/// fixes done and the remaining diagnostics. Fixes which leave the buffer unchanged are ignored.
+pub fn fix_all(
+    url: &Url,
+    buffer: &mut String,
+    settings: &Settings,
+) -> Result<(usize, Vec<Diagnostic>), JournalintError> {
+    let mut num_fixed = 0;
+    'outer: loop {
+        let (journal, diagnostics) = parse_and_lint(url, buffer, settings);
+        if MAX_FIXES <= num_fixed {
+            return Ok((num_fixed, diagnostics));
+        }
+        for diagnostic in diagnostics.iter() {
+            if fix_violation(url, journal.as_ref(), diagnostic, settings, buffer)? {
+                num_fixed += 1;
+                continue 'outer;
+            }
+        }
+        return Ok((num_fixed, diagnostics));
+    }
+}
+
+fn fix_violation(
+    url: &Url,
+    journal: Option<&Expr>,
+    diagnostic: &Diagnostic,
+    settings: &Settings,
+    buffer: &mut String,
+) -> Result<bool, JournalintError> {
+    // Check if there is a default auto-fix command for the rule violation.
+    let (Some(journal), Some(command)) = (journal, get_default_autofix(diagnostic.rule())) else {
+        return Ok(false); // unavailable
+    };
+
+    // Execute the default auto-fix command.
+    let Some(text_edit) = command.execute(url, buffer, journal, diagnostic.span(), settings)?
+    else {
+        return Ok(false);
+    };
+
+    // Apply the fix to on-memory buffer unless it changes nothing, such as a recalculated
+    // duration which is written in the same way as before.
+    let mut fixed = buffer.clone();
+    text_edit.apply(&mut fixed);
+    if fixed == *buffer {
+        return Ok(false);
+    }
+    *buffer = fixed;
+    Ok(true)
+}
+
+/// Get default auto-fix command for the rule violation.
+pub fn get_default_autofix(rule: &Rule) -> Option<AutofixCommand> {
+    match rule {
+        Rule::ParseError => None,
+        Rule::MismatchedDates => Some(AutofixCommand::UseDateInFilename),
+        Rule::MismatchedStartTime => None,
+        Rule::MismatchedEndTime => None,
+        Rule::InvalidStartTime => None,
+        Rule::InvalidEndTime => None,
+        Rule::MissingDate => None,
+        Rule::MissingStartTime => None,
+        Rule::MissingEndTime => None,
+        Rule::TimeJumped => Some(AutofixCommand::ReplaceWithPreviousEndTime),
+        Rule::NegativeTimeRange => None,
+        Rule::IncorrectDuration => Some(AutofixCommand::RecalculateDuration),
+        Rule::OverlappingEntries => None,
+        Rule::UnsortedEntries => Some(AutofixCommand::SortEntries),
+        Rule::UnaccountedTime => None,
+        Rule::UnusedSuppression => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fix_all_with_non_ascii_activities() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let mut buffer = concat!(
+            "---\n",
+            "date: 2006-01-03\n",
+            "start: 09:00\n",
+            "end: 12:00\n",
+            "---\n",
+            "# 午前の作業\n",
+            "- 10:00-11:00 1.00 設計: レビュー\n",
+            "- 09:00-10:00 2.00 会議: 定例\n",
+            "- 11:30-12:00 1.00 実装: 修正\n",
+        )
+        .to_string();
+        let (num_fixed, diagnostics) = fix_all(&url, &mut buffer, &Settings::default()).unwrap();
+        assert_eq!(
+            buffer,
+            concat!(
+                "---\n",
+                "date: 2006-01-02\n",
+                "start: 09:00\n",
+                "end: 12:00\n",
+                "---\n",
+                "# 午前の作業\n",
+                "- 09:00-10:00 1.00 会議: 定例\n",
+                "- 10:00-11:00 1.00 設計: レビュー\n",
+                "- 11:00-12:00 1.00 実装: 修正\n",
+            )
+        );
+        assert_eq!(num_fixed, 4);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn fix_all_with_inexact_duration() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let content = concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 09:00\n",
+            "end: 09:20\n",
+            "---\n",
+            "- 09:00-09:20 0.30 foo\n",
+        );
+        let mut buffer = content.to_string();
+        let (num_fixed, diagnostics) = fix_all(&url, &mut buffer, &Settings::default()).unwrap();
+        assert_eq!(buffer, content.replace("0.30", "0.33"));
+        assert_eq!(num_fixed, 1);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule(), &Rule::IncorrectDuration);
+    }
+}
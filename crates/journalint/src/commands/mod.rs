@@ -1,7 +1,9 @@
 //! This module provides commands of journalint language server.
 mod autofix;
+mod fix_all;
 mod recalculate_duration;
 mod replace_with_previous_end_time;
+mod sort_entries;
 mod use_date_in_filename_visitor;
 
 use std::ops::Range;
@@ -10,8 +12,10 @@ use lsp_types::Url;
 
 use journalint_parse::ast::Expr;
 use journalint_parse::rule::Rule;
+use journalint_parse::settings::Settings;
 
 pub use crate::commands::autofix::AutofixCommand;
+pub use crate::commands::fix_all::{fix_all, get_default_autofix};
 use crate::errors::JournalintError;
 use crate::text_edit::TextEdit;
 
@@ -36,7 +40,9 @@ pub trait Command {
     fn execute(
         &self,
         url: &Url,
+        content: &str,
         ast_root: &Expr,
         selection: &Range<usize>,
+        settings: &Settings,
     ) -> Result<Option<TextEdit>, JournalintError>;
 }
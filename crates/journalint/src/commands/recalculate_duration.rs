@@ -5,6 +5,7 @@ use chrono::prelude::NaiveDate;
 use lsp_types::Url;
 
 use journalint_parse::ast::{walk, Expr, LooseTime, Visitor};
+use journalint_parse::settings::Settings;
 
 use crate::errors::JournalintError;
 use crate::text_edit::TextEdit;
@@ -78,6 +79,7 @@ pub(super) fn execute(
     _url: &Url,
     ast_root: &Expr,
     selection: &Range<usize>,
+    settings: &Settings,
 ) -> Result<Option<TextEdit>, JournalintError> {
     // Determine where to edit.
     let mut visitor = RecalculateDurationVisitor::new(selection.clone());
@@ -107,11 +109,10 @@ pub(super) fn execute(
             name: "end_time".to_string(),
         })
         .and_then(|t| t.to_datetime(date).map_err(JournalintError::from))?;
-    let new_value = end_time - start_time;
-    let new_value = (new_value.num_seconds() as f64) / 3600.0;
+    let new_value = (end_time - start_time).to_std().unwrap_or_default();
 
     Ok(Some(TextEdit::new(
         span_to_replace,
-        format!("{new_value:1.2}"),
+        settings.format_duration(&new_value),
     )))
 }
@@ -0,0 +1,191 @@
+use std::ops::Range;
+
+use chrono::NaiveDate;
+use lsp_types::Url;
+
+use journalint_parse::ast::{walk, Expr, LooseTime, SuppressionKind, Visitor};
+
+use crate::errors::JournalintError;
+use crate::text_edit::TextEdit;
+
+#[derive(Debug, Default)]
+struct SortEntriesVisitor {
+    fm_date: Option<NaiveDate>,
+    curr_start_time: Option<LooseTime>,
+
+    /// Start time and span of each entry.
+    entries: Vec<(LooseTime, Range<usize>)>,
+
+    /// Spans of `journalint-disable-next-line` comments.
+    next_line_suppressions: Vec<Range<usize>>,
+}
+
+impl Visitor<JournalintError> for SortEntriesVisitor {
+    fn on_visit_fm_date(
+        &mut self,
+        value: &NaiveDate,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.fm_date = Some(*value);
+        Ok(())
+    }
+
+    fn on_visit_start_time(
+        &mut self,
+        value: &LooseTime,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_start_time = Some(value.clone());
+        Ok(())
+    }
+
+    fn on_leave_entry(&mut self, span: &Range<usize>) -> Result<(), JournalintError> {
+        if let Some(start_time) = self.curr_start_time.take() {
+            self.entries.push((start_time, span.clone()));
+        }
+        Ok(())
+    }
+
+    fn on_visit_suppression(
+        &mut self,
+        kind: &SuppressionKind,
+        _rules: &[String],
+        span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        if *kind == SuppressionKind::NextLine {
+            self.next_line_suppressions.push(span.clone());
+        }
+        Ok(())
+    }
+}
+
+pub(super) fn execute(
+    _url: &Url,
+    content: &str,
+    ast_root: &Expr,
+) -> Result<Option<TextEdit>, JournalintError> {
+    // Collect entries, each along with the suppression comment on the line before it so that
+    // the comment keeps suppressing violations on the same entry
+    let mut visitor = SortEntriesVisitor::default();
+    walk(ast_root, &mut visitor)?;
+    let chars: Vec<char> = content.chars().collect();
+    let entries: Vec<(LooseTime, Range<usize>)> = visitor
+        .entries
+        .into_iter()
+        .map(|(start_time, span)| {
+            let suppression = visitor
+                .next_line_suppressions
+                .iter()
+                .find(|s| s.end <= span.start && is_line_break(&chars[s.end..span.start]));
+            match suppression {
+                Some(s) => (start_time, s.start..span.end),
+                None => (start_time, span),
+            }
+        })
+        .collect();
+    let (Some((_, first)), Some((_, last))) = (entries.first(), entries.last()) else {
+        return Ok(None);
+    };
+
+    // Determine the order of entries, giving up if any start time cannot be compared
+    let date = visitor.fm_date.unwrap_or_default();
+    let mut keys = Vec::with_capacity(entries.len());
+    for (start_time, _) in &entries {
+        let Ok(key) = start_time.to_datetime(date) else {
+            return Ok(None);
+        };
+        keys.push(key);
+    }
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| keys[i]);
+    if order.iter().enumerate().all(|(i, &j)| i == j) {
+        return Ok(None);
+    }
+
+    // Put the entry lines in order, keeping other lines between them as they are
+    let mut new_text = String::new();
+    let mut prev_end = first.start;
+    for ((_, slot), &i) in entries.iter().zip(order.iter()) {
+        let (_, span) = &entries[i];
+        new_text.extend(&chars[prev_end..slot.start]);
+        new_text.extend(&chars[span.clone()]);
+        prev_end = slot.end;
+    }
+
+    Ok(Some(TextEdit::new(first.start..last.end, new_text)))
+}
+
+/// Check whether the characters are whitespaces including exactly one line break.
+fn is_line_break(chars: &[char]) -> bool {
+    chars.iter().all(|c| c.is_whitespace()) && chars.iter().filter(|&&c| c == '\n').count() == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::parse::parse;
+    use journalint_parse::settings::Settings;
+
+    fn sort(content: &str) -> Option<String> {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (journal, _errors) = parse(content, &Settings::default());
+        let text_edit = execute(&url, content, &journal.unwrap()).unwrap()?;
+        let mut buffer = content.to_string();
+        text_edit.apply(&mut buffer);
+        Some(buffer)
+    }
+
+    const FRONT_MATTER: &str = "---\ndate: 2006-01-02\nstart: 09:00\nend: 25:00\n---\n";
+
+    #[test]
+    fn keep_non_target_lines() {
+        let content = format!(
+            "{FRONT_MATTER}# A\n- 24:00-25:00 1.00 c\n- 10:00-24:00 14.00 b\n# B\n- 09:00-10:00 1.00 a\n"
+        );
+        assert_eq!(
+            sort(&content),
+            Some(format!(
+                "{FRONT_MATTER}# A\n- 09:00-10:00 1.00 a\n- 10:00-24:00 14.00 b\n# B\n- 24:00-25:00 1.00 c\n"
+            ))
+        );
+    }
+
+    #[test]
+    fn non_ascii_activities() {
+        let content = format!(
+            "{FRONT_MATTER}# 作業\n- 10:00-25:00 15.00 実装: 修正\n- 09:00-10:00 1.00 会議: 定例\n"
+        );
+        assert_eq!(
+            sort(&content),
+            Some(format!(
+                "{FRONT_MATTER}# 作業\n- 09:00-10:00 1.00 会議: 定例\n- 10:00-25:00 15.00 実装: 修正\n"
+            ))
+        );
+    }
+
+    #[test]
+    fn move_next_line_suppressions() {
+        let content = format!(
+            "{FRONT_MATTER}<!-- journalint-disable incorrect-duration -->\n\
+             <!-- journalint-disable-next-line incorrect-duration -->\n\
+             - 10:00-25:00 1.00 b\n\
+             - 09:00-10:00 1.00 a\n"
+        );
+        assert_eq!(
+            sort(&content),
+            Some(format!(
+                "{FRONT_MATTER}<!-- journalint-disable incorrect-duration -->\n\
+                 - 09:00-10:00 1.00 a\n\
+                 <!-- journalint-disable-next-line incorrect-duration -->\n\
+                 - 10:00-25:00 1.00 b\n"
+            ))
+        );
+    }
+
+    #[test]
+    fn sorted() {
+        let content = format!("{FRONT_MATTER}- 09:00-10:00 1.00 a\n- 10:00-25:00 15.00 b\n");
+        assert_eq!(sort(&content), None);
+    }
+}
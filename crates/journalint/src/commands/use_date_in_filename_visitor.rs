@@ -5,6 +5,7 @@ use chrono::NaiveDate;
 use lsp_types::Url;
 
 use journalint_parse::ast::{walk, Expr, Visitor};
+use journalint_parse::settings::Settings;
 
 use crate::errors::JournalintError;
 use crate::text_edit::TextEdit;
@@ -31,7 +32,11 @@ impl Visitor<JournalintError> for UseDateInFilenameVisitor {
     }
 }
 
-pub(super) fn execute(url: &Url, ast_root: &Expr) -> Result<Option<TextEdit>, JournalintError> {
+pub(super) fn execute(
+    url: &Url,
+    ast_root: &Expr,
+    settings: &Settings,
+) -> Result<Option<TextEdit>, JournalintError> {
     // Determine where to edit.
     let mut visitor = UseDateInFilenameVisitor::default();
     walk(ast_root, &mut visitor)?;
@@ -42,7 +47,10 @@ pub(super) fn execute(url: &Url, ast_root: &Expr) -> Result<Option<TextEdit>, Jo
         .file_stem()
         .and_then(|stem| stem.to_str())
         .ok_or_else(|| JournalintError::UnsupportedUrl { url: url.clone() })
-        .and_then(|stem| NaiveDate::parse_from_str(stem, "%Y-%m-%d").map_err(JournalintError::from))
+        .and_then(|stem| {
+            NaiveDate::parse_from_str(stem, &settings.filename_date_format)
+                .map_err(JournalintError::from)
+        })
         .map(|date| date.format("%Y-%m-%d").to_string())?;
 
     Ok(Some(TextEdit::new(range_to_replace.clone(), new_value)))
@@ -0,0 +1,403 @@
+//! Provides completion of start times, codes and activity prefixes in entry lines.
+use std::collections::HashMap;
+use std::fs::{read_dir, read_to_string};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use log::warn;
+
+use journalint_parse::parse::parse;
+use journalint_parse::settings::Settings;
+
+use crate::journal_file::journal_file_date;
+use crate::journal_info::{split_activity_prefixes, JournalInfo};
+
+/// Maximum number of journal files in the workspace to collect words from.
+const MAX_WORKSPACE_JOURNALS: usize = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// End time of the previous entry as the start time of a new entry.
+    StartTime,
+    Code,
+    ActivityPrefix,
+}
+
+/// Candidate text to replace what is being typed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    pub label: String,
+    pub new_text: String,
+
+    /// Span of the text to be replaced with `new_text`.
+    pub span: Range<usize>,
+}
+
+/// Codes and activity prefixes used in journals, scored by how often and how recently they were
+/// used.
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+    codes: HashMap<String, f64>,
+    activity_prefixes: HashMap<String, f64>,
+}
+
+impl Vocabulary {
+    /// Add codes and activity prefixes in a journal, scoring each occurrence by `weight`.
+    pub fn add(&mut self, info: &JournalInfo, weight: f64) {
+        for entry in &info.entries {
+            for code in &entry.codes {
+                *self.codes.entry(code.clone()).or_default() += weight;
+            }
+            let Some(activity) = &entry.activity else {
+                continue;
+            };
+            let (prefixes, _) = split_activity_prefixes(activity);
+            for i in 1..=prefixes.len() {
+                let prefix = format!("{}: ", prefixes[..i].join(": "));
+                *self.activity_prefixes.entry(prefix).or_default() += weight;
+            }
+        }
+    }
+
+    /// Add codes and activity prefixes in the document being edited, except the entry at the
+    /// cursor which is still being typed.
+    pub fn add_document(&mut self, info: &JournalInfo, cursor: usize) {
+        let mut info = info.clone();
+        info.entries
+            .retain(|e| !(e.span.start <= cursor && cursor <= e.span.end));
+        self.add(&info, 1.0);
+    }
+
+    /// Add codes and activity prefixes in the other journal files in the workspace of the
+    /// document at `path`, scoring the ones in newer files higher.
+    pub fn add_workspace_journals(&mut self, path: &Path, workspace: &WorkspaceJournals) {
+        let journals = workspace.journals.iter().filter(|(p, _)| p != path);
+        for (rank, (_, info)) in journals.take(MAX_WORKSPACE_JOURNALS).enumerate() {
+            // The document itself is scored 1.0 per occurrence
+            self.add(info, 1.0 / (rank as f64 + 2.0));
+        }
+    }
+
+    fn ranked_codes(&self) -> Vec<&str> {
+        ranked(&self.codes)
+    }
+
+    fn ranked_activity_prefixes(&self) -> Vec<&str> {
+        ranked(&self.activity_prefixes)
+    }
+}
+
+/// Facts collected from the newest journal files in a workspace folder, or in a directory if
+/// the document is not in any workspace folder.
+///
+/// This is meant to be cached per workspace folder (or directory) since reading the files on
+/// every completion request is slow.
+#[derive(Debug, Default)]
+pub struct WorkspaceJournals {
+    /// Paths and facts of the journal files, newest first.
+    journals: Vec<(PathBuf, JournalInfo)>,
+}
+
+impl WorkspaceJournals {
+    /// Read the newest journal files in a directory, searching its subdirectories too (except
+    /// hidden ones such as `.git`) if `recursive`.
+    pub fn read(dir: &Path, recursive: bool, settings: &Settings) -> Self {
+        let mut paths: Vec<(NaiveDate, PathBuf)> = Vec::new();
+        collect_journal_paths(dir, recursive, settings, &mut paths);
+        paths.sort_by(|a, b| b.cmp(a));
+
+        // Read one more file in case the document being edited is one of them
+        let mut journals = Vec::new();
+        for (_, path) in paths.into_iter().take(MAX_WORKSPACE_JOURNALS + 1) {
+            let content = match read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("Failed to read {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            let (Some(journal), _) = parse(&content, settings) else {
+                continue;
+            };
+            if let Ok(info) = JournalInfo::collect(&journal) {
+                journals.push((path, info));
+            }
+        }
+        Self { journals }
+    }
+}
+
+/// Collect paths of journal files in a directory along with their dates.
+fn collect_journal_paths(
+    dir: &Path,
+    recursive: bool,
+    settings: &Settings,
+    paths: &mut Vec<(NaiveDate, PathBuf)>,
+) {
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to read directory {:?}: {}", dir, e);
+            return;
+        }
+    };
+    for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+        if path.is_dir() {
+            let hidden = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .is_some_and(|s| s.starts_with('.'));
+            if recursive && !hidden {
+                collect_journal_paths(&path, recursive, settings, paths);
+            }
+        } else if let Some(date) = journal_file_date(&path, settings) {
+            paths.push((date, path));
+        }
+    }
+}
+
+/// Sort words by their scores in descending order.
+fn ranked(scores: &HashMap<String, f64>) -> Vec<&str> {
+    let mut words: Vec<(&str, f64)> = scores.iter().map(|(w, s)| (w.as_str(), *s)).collect();
+    words.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    words.into_iter().map(|(w, _)| w).collect()
+}
+
+/// Part of an entry line being typed.
+#[derive(Debug, PartialEq, Eq)]
+enum Context<'a> {
+    StartTime(&'a str),
+    Code(&'a str),
+    ActivityPrefix(&'a str),
+}
+
+/// Determine which part of an entry line is being typed from the text before the cursor.
+fn context<'a>(line: &'a str, settings: &Settings) -> Option<Context<'a>> {
+    let rest = line.strip_prefix("- ")?.trim_start();
+    let Some((_time_range, fields)) = rest.split_once(' ') else {
+        // The start time is being typed unless the hyphen after it was typed
+        let is_start_time = rest.chars().all(|c| c.is_ascii_digit() || c == ':');
+        return is_start_time.then_some(Context::StartTime(rest));
+    };
+
+    // Skip the codes to find the duration, which the activity follows
+    let mut fields = fields.trim_start();
+    let mut num_codes = 0;
+    while let Some((word, rest)) = fields.split_once(' ') {
+        if is_duration(word) {
+            return Some(Context::ActivityPrefix(rest.trim_start()));
+        }
+        num_codes += 1;
+        fields = rest.trim_start();
+    }
+    (num_codes < settings.max_codes).then_some(Context::Code(fields))
+}
+
+fn is_duration(word: &str) -> bool {
+    word.contains('.') && word.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Make completions for the text before the cursor in a line.
+///
+/// `line_start` is the offset of the line in the document.
+pub fn complete(
+    line: &str,
+    line_start: usize,
+    info: &JournalInfo,
+    vocabulary: &Vocabulary,
+    settings: &Settings,
+) -> Vec<Completion> {
+    let cursor = line_start + line.chars().count();
+    let completion = |kind, label: &str, new_text: String, typed: &str| Completion {
+        kind,
+        label: label.to_string(),
+        new_text,
+        span: cursor - typed.chars().count()..cursor,
+    };
+    match context(line, settings) {
+        Some(Context::StartTime(typed)) => info
+            .entries
+            .iter()
+            .rev()
+            .find(|e| e.span.end <= line_start)
+            .and_then(|e| e.end_time.as_ref())
+            .filter(|end_time| end_time.as_str().starts_with(typed))
+            .map(|end_time| {
+                let label = end_time.as_str();
+                completion(CompletionKind::StartTime, label, format!("{label}-"), typed)
+            })
+            .into_iter()
+            .collect(),
+        Some(Context::Code(typed)) => vocabulary
+            .ranked_codes()
+            .into_iter()
+            .filter(|code| code.to_lowercase().starts_with(&typed.to_lowercase()))
+            .map(|code| completion(CompletionKind::Code, code, format!("{code} "), typed))
+            .collect(),
+        Some(Context::ActivityPrefix(typed)) => vocabulary
+            .ranked_activity_prefixes()
+            .into_iter()
+            .filter(|prefix| prefix.to_lowercase().starts_with(&typed.to_lowercase()))
+            .map(|prefix| {
+                completion(
+                    CompletionKind::ActivityPrefix,
+                    prefix,
+                    prefix.to_string(),
+                    typed,
+                )
+            })
+            .collect(),
+        None => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use rstest::*;
+
+    #[rstest]
+    #[case("", None)]
+    #[case("# - ", None)]
+    #[case("- ", Some(Context::StartTime("")))]
+    #[case("- 10:1", Some(Context::StartTime("10:1")))]
+    #[case("- 10:15-1", None)]
+    #[case("- 10:15-11:00 ", Some(Context::Code("")))]
+    #[case("- 10:15-11:00 AB", Some(Context::Code("AB")))]
+    #[case("- 10:15-11:00 AB X", Some(Context::Code("X")))]
+    #[case("- 10:15-11:00 AB X Y", None)]
+    #[case("- 10:15-11:00 AB 0.75 ", Some(Context::ActivityPrefix("")))]
+    #[case("- 10:15-11:00 0.75 foo: b", Some(Context::ActivityPrefix("foo: b")))]
+    fn context(#[case] line: &str, #[case] want: Option<Context>) {
+        assert_eq!(super::context(line, &Settings::default()), want);
+    }
+
+    fn journal_info(content: &str) -> JournalInfo {
+        let (journal, _errors) = parse(content, &Settings::default());
+        JournalInfo::collect(&journal.unwrap()).unwrap()
+    }
+
+    const CONTENT: &str = concat!(
+        "---\n",
+        "date: 2006-01-02\n",
+        "start: 09:00\n",
+        "end: 25:00\n",
+        "---\n",
+        "- 09:00-10:00 ABC 1.00 foo: bar: baz\n",
+        "- 10:00-24:30 XYZ 14.50 foo: qux\n",
+    );
+
+    #[test]
+    fn vocabulary() {
+        let mut vocabulary = Vocabulary::default();
+        vocabulary.add(&journal_info(CONTENT), 1.0);
+        vocabulary.add(
+            &journal_info(&CONTENT.replace("ABC", "XYZ").replace("foo: bar: ", "")),
+            0.5,
+        );
+        assert_eq!(vocabulary.ranked_codes(), vec!["XYZ", "ABC"]);
+        assert_eq!(
+            vocabulary.ranked_activity_prefixes(),
+            vec!["foo: ", "foo: bar: "]
+        );
+    }
+
+    #[test]
+    fn vocabulary_from_workspace_journals() {
+        let workspace = WorkspaceJournals {
+            journals: vec![
+                (PathBuf::from("/j/2006-01-03.md"), journal_info(CONTENT)),
+                (
+                    PathBuf::from("/j/2006-01-02.md"),
+                    journal_info(&CONTENT.replace("ABC", "XYZ")),
+                ),
+            ],
+        };
+
+        // The document itself is not counted twice
+        let mut vocabulary = Vocabulary::default();
+        vocabulary.add_workspace_journals(Path::new("/j/2006-01-03.md"), &workspace);
+        assert_eq!(vocabulary.ranked_codes(), vec!["XYZ"]);
+
+        let mut vocabulary = Vocabulary::default();
+        vocabulary.add_workspace_journals(Path::new("/j/2006-01-04.md"), &workspace);
+        assert_eq!(vocabulary.ranked_codes(), vec!["XYZ", "ABC"]);
+    }
+
+    #[rstest]
+    #[case(false, vec!["ABC", "XYZ"])]
+    #[case(true, vec!["XYZ", "NESTED", "ABC"])]
+    fn read_workspace_journals(#[case] recursive: bool, #[case] want: Vec<&str>) {
+        let root = std::env::temp_dir().join(format!(
+            "journalint-completion-{}-{recursive}",
+            std::process::id()
+        ));
+        let nested = root.join("2006").join("01");
+        create_dir_all(&nested).unwrap();
+        write(root.join("2006-01-02.md"), CONTENT).unwrap();
+        write(
+            nested.join("2006-01-03.md"),
+            CONTENT.replace("ABC", "NESTED"),
+        )
+        .unwrap();
+        write(nested.join("notes.md"), CONTENT.replace("ABC", "NOTES")).unwrap();
+
+        let workspace = WorkspaceJournals::read(&root, recursive, &Settings::default());
+        remove_dir_all(&root).unwrap();
+        let mut vocabulary = Vocabulary::default();
+        vocabulary.add_workspace_journals(&root.join("2006-01-04.md"), &workspace);
+        assert_eq!(vocabulary.ranked_codes(), want);
+    }
+
+    /// Complete the line typed at the end of `CONTENT`.
+    fn complete_line(line: &str) -> Vec<(String, String, Range<usize>)> {
+        let content = format!("{CONTENT}{line}\n");
+        let info = journal_info(&content);
+        let line_start = CONTENT.len();
+        let mut vocabulary = Vocabulary::default();
+        vocabulary.add_document(&info, line_start + line.chars().count());
+        complete(line, line_start, &info, &vocabulary, &Settings::default())
+            .into_iter()
+            .map(|c| {
+                (
+                    c.label,
+                    c.new_text,
+                    c.span.start - line_start..c.span.end - line_start,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn complete_start_time() {
+        assert_eq!(
+            complete_line("- 2"),
+            vec![("24:30".to_string(), "24:30-".to_string(), 2..3)]
+        );
+        assert_eq!(complete_line("- 1"), vec![]);
+    }
+
+    #[test]
+    fn complete_code() {
+        assert_eq!(
+            complete_line("- 24:30-25:00 a"),
+            vec![("ABC".to_string(), "ABC ".to_string(), 14..15)]
+        );
+    }
+
+    #[test]
+    fn complete_activity_prefix() {
+        assert_eq!(
+            complete_line("- 24:30-25:00 0.50 foo: "),
+            vec![
+                ("foo: ".to_string(), "foo: ".to_string(), 19..24),
+                ("foo: bar: ".to_string(), "foo: bar: ".to_string(), 19..24),
+            ]
+        );
+    }
+}
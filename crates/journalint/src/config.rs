@@ -0,0 +1,101 @@
+//! Loading configuration files.
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use journalint_parse::settings::{is_valid_time_format, Settings};
+
+use crate::errors::JournalintError;
+
+/// Name of the configuration file.
+pub const CONFIG_FILENAME: &str = "journalint.toml";
+
+/// Find a configuration file by walking up the directory tree from the given directory.
+pub fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(CONFIG_FILENAME))
+        .find(|path| path.is_file())
+}
+
+/// Load settings from a configuration file.
+pub fn load_settings(path: &Path) -> Result<Settings, JournalintError> {
+    let content = read_to_string(path)?;
+    parse_settings(&content, path)
+}
+
+/// Parse and validate the content of a configuration file.
+fn parse_settings(content: &str, path: &Path) -> Result<Settings, JournalintError> {
+    let settings: Settings =
+        toml::from_str(content).map_err(|e| JournalintError::InvalidConfig {
+            path: path.to_path_buf(),
+            reason: e.to_string(),
+        })?;
+    if !is_valid_time_format(&settings.time_format) {
+        return Err(JournalintError::InvalidConfig {
+            path: path.to_path_buf(),
+            reason: format!("invalid time-format {:?}", settings.time_format),
+        });
+    }
+    Ok(settings)
+}
+
+/// Determine settings for a journal file or a directory.
+///
+/// If `config` is given, settings are loaded from it. Otherwise a configuration file is searched
+/// from the directory of the journal file (or the directory itself) up to the root, and the
+/// `fallback` is used if none was found.
+pub fn resolve_settings(
+    config: Option<&Path>,
+    path: &Path,
+    fallback: &Settings,
+) -> Result<Settings, JournalintError> {
+    if let Some(config) = config {
+        return load_settings(config);
+    }
+    let dir = if path.is_dir() {
+        Some(path)
+    } else {
+        path.parent()
+    };
+    match dir.and_then(find_config_file) {
+        Some(config) => load_settings(&config),
+        None => Ok(fallback.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let settings: Settings = toml::from_str(concat!(
+            "filename-date-format = \"%Y%m%d\"\n",
+            "max-codes = 3\n",
+        ))
+        .unwrap();
+        assert_eq!(
+            settings,
+            Settings {
+                filename_date_format: "%Y%m%d".to_string(),
+                max_codes: 3,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_config_with_unknown_key() {
+        let result: Result<Settings, _> = toml::from_str("max-code = 3\n");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_config_with_invalid_time_format() {
+        let result = parse_settings("time-format = \"%Q\"\n", Path::new(CONFIG_FILENAME));
+        assert!(matches!(result, Err(JournalintError::InvalidConfig { .. })));
+
+        // Time values in 12-hour clock cannot be read back by the parser
+        let result = parse_settings("time-format = \"%-I:%M %p\"\n", Path::new(CONFIG_FILENAME));
+        assert!(matches!(result, Err(JournalintError::InvalidConfig { .. })));
+    }
+}
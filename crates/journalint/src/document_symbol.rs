@@ -0,0 +1,284 @@
+//! Provides the outline of journals.
+use std::ops::Range;
+
+use chrono::NaiveDate;
+
+use journalint_parse::ast::{walk, Expr, LooseTime, Visitor};
+
+use crate::errors::JournalintError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    FrontMatter,
+    Heading,
+    Entry,
+}
+
+/// Element of the outline of a journal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub name: String,
+    pub detail: Option<String>,
+
+    /// Span of the element including its children.
+    pub span: Range<usize>,
+
+    /// Span of the element itself, such as the heading line.
+    pub selection_span: Range<usize>,
+
+    pub children: Vec<Symbol>,
+}
+
+/// Make the outline of a journal.
+///
+/// Entries are grouped under the headings preceding them, and headings are nested by their
+/// levels.
+pub fn document_symbols(journal: &Expr) -> Result<Vec<Symbol>, JournalintError> {
+    let mut visitor = DocumentSymbolCollector::default();
+    walk(journal, &mut visitor)?;
+    visitor.close_headings(0);
+    Ok(visitor.symbols)
+}
+
+#[derive(Default)]
+struct DocumentSymbolCollector {
+    fm_date: Option<NaiveDate>,
+    fm_start: Option<LooseTime>,
+    fm_end: Option<LooseTime>,
+    curr_start: Option<LooseTime>,
+    curr_end: Option<LooseTime>,
+    curr_codes: Vec<String>,
+    curr_activity: Option<String>,
+
+    /// Headings not closed yet along with their levels, from the outermost one.
+    headings: Vec<(usize, Symbol)>,
+
+    /// Symbols at the top level.
+    symbols: Vec<Symbol>,
+}
+
+impl DocumentSymbolCollector {
+    fn push(&mut self, symbol: Symbol) {
+        match self.headings.last_mut() {
+            Some((_, heading)) => heading.children.push(symbol),
+            None => self.symbols.push(symbol),
+        }
+    }
+
+    /// Close the headings at the level or deeper.
+    fn close_headings(&mut self, level: usize) {
+        while self.headings.last().is_some_and(|(l, _)| level <= *l) {
+            let (_, mut heading) = self.headings.pop().unwrap();
+            if let Some(last_child) = heading.children.last() {
+                heading.span.end = heading.span.end.max(last_child.span.end);
+            }
+            self.push(heading);
+        }
+    }
+}
+
+impl Visitor<JournalintError> for DocumentSymbolCollector {
+    fn on_visit_fm_date(
+        &mut self,
+        value: &NaiveDate,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.fm_date = Some(*value);
+        Ok(())
+    }
+
+    fn on_visit_fm_start(
+        &mut self,
+        value: &LooseTime,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.fm_start = Some(value.clone());
+        Ok(())
+    }
+
+    fn on_visit_fm_end(
+        &mut self,
+        value: &LooseTime,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.fm_end = Some(value.clone());
+        Ok(())
+    }
+
+    fn on_leave_fm(&mut self, span: &Range<usize>) -> Result<(), JournalintError> {
+        let name = match self.fm_date {
+            Some(date) => date.format("%Y-%m-%d").to_string(),
+            None => "Front matter".to_string(),
+        };
+        let detail = match (&self.fm_start, &self.fm_end) {
+            (Some(start), Some(end)) => Some(format!("{}-{}", start.as_str(), end.as_str())),
+            _ => None,
+        };
+        self.push(Symbol {
+            kind: SymbolKind::FrontMatter,
+            name,
+            detail,
+            span: span.clone(),
+            selection_span: span.clone(),
+            children: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn on_visit_heading(
+        &mut self,
+        level: usize,
+        title: &str,
+        span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        // Clients may reject symbols without a name
+        let name = if title.is_empty() {
+            "#".repeat(level)
+        } else {
+            title.to_string()
+        };
+        self.close_headings(level);
+        self.headings.push((
+            level,
+            Symbol {
+                kind: SymbolKind::Heading,
+                name,
+                detail: None,
+                span: span.clone(),
+                selection_span: span.clone(),
+                children: Vec::new(),
+            },
+        ));
+        Ok(())
+    }
+
+    fn on_visit_entry(&mut self, _span: &Range<usize>) -> Result<(), JournalintError> {
+        self.curr_start = None;
+        self.curr_end = None;
+        self.curr_codes.clear();
+        self.curr_activity = None;
+        Ok(())
+    }
+
+    fn on_visit_start_time(
+        &mut self,
+        value: &LooseTime,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_start = Some(value.clone());
+        Ok(())
+    }
+
+    fn on_visit_end_time(
+        &mut self,
+        value: &LooseTime,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_end = Some(value.clone());
+        Ok(())
+    }
+
+    fn on_visit_code(&mut self, value: &str, _span: &Range<usize>) -> Result<(), JournalintError> {
+        self.curr_codes.push(value.to_string());
+        Ok(())
+    }
+
+    fn on_visit_activity(
+        &mut self,
+        value: &str,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_activity = Some(value.to_string());
+        Ok(())
+    }
+
+    fn on_leave_entry(&mut self, span: &Range<usize>) -> Result<(), JournalintError> {
+        let time_range = match (&self.curr_start, &self.curr_end) {
+            (Some(start), Some(end)) => format!("{}-{}", start.as_str(), end.as_str()),
+            _ => String::new(),
+        };
+        let name = match self.curr_activity.as_deref() {
+            Some(activity) if !activity.is_empty() => format!("{time_range} {activity}"),
+            _ => time_range,
+        };
+        let detail = (!self.curr_codes.is_empty()).then(|| self.curr_codes.join(" "));
+        self.push(Symbol {
+            kind: SymbolKind::Entry,
+            name,
+            detail,
+            span: span.clone(),
+            selection_span: span.clone(),
+            children: Vec::new(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::parse::parse;
+    use journalint_parse::settings::Settings;
+
+    /// Simplify symbols into (name, detail, children) for comparison.
+    fn outline(symbols: &[Symbol]) -> Vec<(String, Option<String>, Vec<String>)> {
+        symbols
+            .iter()
+            .map(|s| {
+                let children = s.children.iter().map(|c| c.name.clone()).collect();
+                (s.name.clone(), s.detail.clone(), children)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested() {
+        let content = concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 09:00\n",
+            "end: 12:00\n",
+            "---\n",
+            "# Morning\n",
+            "- 09:00-10:00 A B 1.00 foo: bar\n",
+            "## Meetings\n",
+            "- 10:00-11:00 1.00 weekly\n",
+            "# Afternoon\n",
+            "- 11:00-12:00 1.00 baz\n",
+        );
+        let (journal, _errors) = parse(content, &Settings::default());
+        let symbols = document_symbols(&journal.unwrap()).unwrap();
+        assert_eq!(
+            outline(&symbols),
+            vec![
+                (
+                    "2006-01-02".to_string(),
+                    Some("09:00-12:00".to_string()),
+                    vec![]
+                ),
+                (
+                    "Morning".to_string(),
+                    None,
+                    vec!["09:00-10:00 foo: bar".to_string(), "Meetings".to_string()]
+                ),
+                (
+                    "Afternoon".to_string(),
+                    None,
+                    vec!["11:00-12:00 baz".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(symbols[1].children[0].detail, Some("A B".to_string()));
+
+        // A heading encloses its descendants
+        let morning = content.find("# Morning").unwrap();
+        let weekly_end = content.find("weekly").unwrap() + "weekly".len();
+        assert_eq!(symbols[1].span, morning..weekly_end);
+        assert_eq!(
+            symbols[1].children[1].children[0].name,
+            "10:00-11:00 weekly"
+        );
+    }
+}
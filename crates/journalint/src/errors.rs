@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use crossbeam_channel::SendError;
 use journalint_parse::errors::UnknownRule;
 use lsp_server::Message;
@@ -9,6 +11,24 @@ use journalint_parse::errors::InvalidTimeValueError;
 
 // ----------------------------------------------------------------------------
 
+// Exit status policy of the CLI:
+//
+// - 0: No rule violations found (or the number of them is within `--max-warnings`)
+// - 1: Rule violations found
+// - 2: Usage error, I/O error or any other failure
+
+/// Exit status of the CLI when rule violations were found.
+pub const E_VIOLATIONS: exitcode::ExitCode = 1;
+
+/// Exit status of the CLI on invalid command line usage.
+pub const E_USAGE: exitcode::ExitCode = 2;
+
+/// Exit status of the CLI on I/O errors.
+pub const E_IOERR: exitcode::ExitCode = 2;
+
+/// Exit status of the CLI on unexpected errors.
+pub const E_UNEXPECTED: exitcode::ExitCode = 2;
+
 /// Error for CLI main function.
 #[derive(Default)]
 pub struct CliError {
@@ -44,21 +64,12 @@ impl CliError {
 
 #[derive(Error, Debug)]
 pub enum JournalintError {
-    #[error("UNEXPECTED ERROR: {0}")]
-    UnexpectedError(String),
-
     #[error("{}", source)]
     UnknownRule {
         #[from]
         source: UnknownRule,
     },
 
-    #[error("Unknown command: {0}")]
-    UnknownCommand(String),
-
-    #[error("Unexpected arguments: {0}")]
-    UnexpectedArguments(String),
-
     #[error("Unsupported URL: {url}")]
     UnsupportedUrl { url: Url },
 
@@ -71,12 +82,21 @@ pub enum JournalintError {
         source: InvalidTimeValueError,
     },
 
+    #[error("Invalid configuration file {path:?}: {reason}")]
+    InvalidConfig { path: PathBuf, reason: String },
+
     #[error("Required value is missing: {name}")]
     MissingRequiredValue { name: String },
 
     #[error("Target not found for command '{command}'")]
     CommandTargetNotFound { command: String },
 
+    #[error("Too many codes to export: {count} (max-codes is {max})")]
+    TooManyCodes { count: usize, max: usize },
+
+    #[error("Invalid data to import at line {line}: {reason}")]
+    InvalidImportData { line: u64, reason: String },
+
     #[error("LSP communication error: {0}")]
     LspCommunicationError(String),
 
@@ -92,6 +112,12 @@ pub enum JournalintError {
         source: serde_json::error::Error,
     },
 
+    #[error("CSV error: {}", .source)]
+    CsvError {
+        #[from]
+        source: csv::Error,
+    },
+
     #[error("Parsing date or time failed: {}", .source)]
     ChronoParseError {
         #[from]
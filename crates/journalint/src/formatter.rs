@@ -0,0 +1,416 @@
+//! Provides formatting of journals.
+//!
+//! Time values are written in `time-format`, durations are written with `duration-precision` digits after
+//! the decimal point, and fields of entries are separated by a single space. Codes and durations
+//! are also aligned in columns if `align-columns` is enabled.
+use std::fmt::Write;
+use std::ops::Range;
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveTime};
+
+use journalint_parse::ast::{walk, Expr, LooseTime, Visitor};
+use journalint_parse::settings::Settings;
+
+use crate::errors::JournalintError;
+use crate::text_edit::TextEdit;
+
+/// Entry line to format, made only of the valid components.
+#[derive(Debug)]
+struct EntryLine {
+    span: Range<usize>,
+    start: String,
+    end: String,
+    codes: Vec<String>,
+    duration: String,
+    activity: String,
+}
+
+struct FormatVisitor<'a> {
+    chars: Vec<char>,
+    settings: &'a Settings,
+
+    /// Time values in the front matter and their spans.
+    fm_start: Option<(String, Range<usize>)>,
+    fm_end: Option<(String, Range<usize>)>,
+
+    /// Whether each entry is formatted, or left as it is, in order.
+    formatted: Vec<bool>,
+
+    curr_start: Option<String>,
+    curr_end: Option<String>,
+    curr_codes: Vec<String>,
+    curr_duration: Option<String>,
+    curr_activity: Option<String>,
+    entries: Vec<EntryLine>,
+}
+
+impl<'a> FormatVisitor<'a> {
+    fn new(content: &str, settings: &'a Settings) -> Self {
+        Self {
+            chars: content.chars().collect(),
+            settings,
+            fm_start: None,
+            fm_end: None,
+            formatted: Vec::new(),
+            curr_start: None,
+            curr_end: None,
+            curr_codes: Vec::new(),
+            curr_duration: None,
+            curr_activity: None,
+            entries: Vec::new(),
+        }
+    }
+
+    fn text(&self, span: &Range<usize>) -> String {
+        self.chars[span.clone()].iter().collect()
+    }
+}
+
+impl Visitor<JournalintError> for FormatVisitor<'_> {
+    fn on_visit_fm_start(
+        &mut self,
+        value: &LooseTime,
+        span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        let time = format_time(value, self.settings).unwrap_or_else(|| self.text(span));
+        self.fm_start = Some((time, span.clone()));
+        Ok(())
+    }
+
+    fn on_visit_fm_end(
+        &mut self,
+        value: &LooseTime,
+        span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        let time = format_time(value, self.settings).unwrap_or_else(|| self.text(span));
+        self.fm_end = Some((time, span.clone()));
+        Ok(())
+    }
+
+    fn on_visit_entry(&mut self, _span: &Range<usize>) -> Result<(), JournalintError> {
+        self.curr_start = None;
+        self.curr_end = None;
+        self.curr_codes.clear();
+        self.curr_duration = None;
+        self.curr_activity = None;
+        Ok(())
+    }
+
+    fn on_visit_start_time(
+        &mut self,
+        value: &LooseTime,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_start = format_time(value, self.settings);
+        Ok(())
+    }
+
+    fn on_visit_end_time(
+        &mut self,
+        value: &LooseTime,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_end = format_time(value, self.settings);
+        Ok(())
+    }
+
+    fn on_visit_code(&mut self, value: &str, _span: &Range<usize>) -> Result<(), JournalintError> {
+        self.curr_codes.push(value.to_string());
+        Ok(())
+    }
+
+    fn on_visit_duration(
+        &mut self,
+        value: &Duration,
+        span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        // Keep the written value if changing the precision changes the value
+        let formatted = self.settings.format_duration(value);
+        let unchanged = str::parse::<f64>(&formatted)
+            .is_ok_and(|hours| Duration::from_secs_f64(hours * 3600.0) == *value);
+        self.curr_duration = Some(if unchanged {
+            formatted
+        } else {
+            self.text(span)
+        });
+        Ok(())
+    }
+
+    fn on_visit_activity(
+        &mut self,
+        value: &str,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_activity = Some(value.trim_end().to_string());
+        Ok(())
+    }
+
+    fn on_leave_entry(&mut self, span: &Range<usize>) -> Result<(), JournalintError> {
+        // Leave entries with invalid components as they are
+        let (Some(start), Some(end), Some(duration), Some(activity)) = (
+            self.curr_start.take(),
+            self.curr_end.take(),
+            self.curr_duration.take(),
+            self.curr_activity.take(),
+        ) else {
+            self.formatted.push(false);
+            return Ok(());
+        };
+        self.formatted.push(true);
+        self.entries.push(EntryLine {
+            span: span.clone(),
+            start,
+            end,
+            codes: std::mem::take(&mut self.curr_codes),
+            duration,
+            activity,
+        });
+        Ok(())
+    }
+}
+
+/// Write a time value in `time-format`, or as zero-padded hours and minutes if it is 24:00 or
+/// later which `time-format` cannot express.
+///
+/// This returns `None` for an invalid time value, or if `time-format` is invalid or writes a
+/// value which the parser cannot read back as the same time.
+fn format_time(value: &LooseTime, settings: &Settings) -> Option<String> {
+    let (h, m) = value.as_str().split_once(':')?;
+    let (h, m) = (str::parse::<u32>(h).ok()?, str::parse::<u32>(m).ok()?);
+    match NaiveTime::from_hms_opt(h, m, 0) {
+        Some(time) => {
+            let mut text = String::new();
+            write!(text, "{}", time.format(&settings.time_format)).ok()?;
+            let date = NaiveDate::default();
+            let read_back = LooseTime::new(text.as_str()).to_datetime(date).ok()?;
+            (read_back.time() == time && read_back.date_naive() == date).then_some(text)
+        }
+        None if 24 <= h && m < 60 => Some(format!("{h:02}:{m:02}")),
+        None => None,
+    }
+}
+
+/// Make edits which format the journal.
+///
+/// Each edit replaces a time value in the front matter or an entry line. Edits are ordered by
+/// their positions and only the ones which change something are made.
+pub fn format(
+    content: &str,
+    journal: &Expr,
+    settings: &Settings,
+) -> Result<Vec<TextEdit>, JournalintError> {
+    let mut visitor = FormatVisitor::new(content, settings);
+    walk(journal, &mut visitor)?;
+
+    // Determine width of the columns to align
+    let mut code_widths: Vec<usize> = Vec::new();
+    let mut duration_width = 0;
+    if settings.align_columns {
+        for entry in &visitor.entries {
+            for (i, code) in entry.codes.iter().enumerate() {
+                let width = code.chars().count();
+                match code_widths.get_mut(i) {
+                    Some(w) => *w = (*w).max(width),
+                    None => code_widths.push(width),
+                }
+            }
+            duration_width = duration_width.max(entry.duration.chars().count());
+        }
+    }
+
+    // Leave the front matter times as they are if the entries which they are compared with are
+    // left as they are, so that formatting does not make them mismatch
+    let mut edits = Vec::new();
+    let fm_times = [
+        (&visitor.fm_start, visitor.formatted.first()),
+        (&visitor.fm_end, visitor.formatted.last()),
+    ];
+    for (fm_time, formatted) in fm_times {
+        let Some((time, span)) = fm_time else {
+            continue;
+        };
+        if formatted != Some(&false) && &visitor.text(span) != time {
+            edits.push(TextEdit::new(span.clone(), time.clone()));
+        }
+    }
+    for entry in &visitor.entries {
+        let mut fields = vec![format!("- {}-{}", entry.start, entry.end)];
+        if settings.align_columns {
+            for (i, width) in code_widths.iter().enumerate() {
+                let code = entry.codes.get(i).map(String::as_str).unwrap_or_default();
+                fields.push(format!("{code:width$}"));
+            }
+            fields.push(format!("{:>duration_width$}", entry.duration));
+        } else {
+            fields.extend(entry.codes.iter().cloned());
+            fields.push(entry.duration.clone());
+        }
+        if !entry.activity.is_empty() {
+            fields.push(entry.activity.clone());
+        }
+        let line = fields.join(" ");
+        if visitor.text(&entry.span) != line {
+            edits.push(TextEdit::new(entry.span.clone(), line));
+        }
+    }
+    Ok(edits)
+}
+
+/// Format the content, returning `None` if it is already formatted.
+pub fn format_content(
+    content: &str,
+    journal: &Expr,
+    settings: &Settings,
+) -> Result<Option<String>, JournalintError> {
+    let edits = format(content, journal, settings)?;
+    if edits.is_empty() {
+        return Ok(None);
+    }
+    let mut buffer = content.to_string();
+    for edit in edits.iter().rev() {
+        edit.apply(&mut buffer);
+    }
+    Ok(Some(buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::parse::parse;
+    use rstest::*;
+
+    fn format_with(content: &str, settings: &Settings) -> Option<String> {
+        let (journal, errors) = parse(content, settings);
+        assert_eq!(errors, []);
+        format_content(content, &journal.unwrap(), settings).unwrap()
+    }
+
+    #[rstest]
+    #[case("9:00", "%H:%M", Some("09:00"))]
+    #[case("09:05", "%-H:%M", Some("9:05"))]
+    #[case("24:30", "%H:%M", Some("24:30"))]
+    #[case("9:60", "%H:%M", None)]
+    #[case("9:0a", "%H:%M", None)]
+    #[case("9:00", "%Q", None)]
+    #[case("9:00", "%-I:%M %p", None)]
+    #[case("13:45", "%I:%M", None)]
+    fn format_time(#[case] value: &str, #[case] time_format: &str, #[case] want: Option<&str>) {
+        let settings = Settings {
+            time_format: time_format.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            super::format_time(&LooseTime::new(value), &settings),
+            want.map(str::to_string)
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        let content = concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 9:00\n",
+            "end: 24:30\n",
+            "---\n",
+            "# 作業\n",
+            "-  9:00-10:15   ABC  1.25 foo: bar  \n",
+            "- 10:15-12:00 1.75 日本語\n",
+            "- 12:00-12:01 0.0167 rounding changes the value\n",
+            "- 12:01-24:30 X 1.5 done\n",
+        );
+        assert_eq!(
+            format_with(content, &Settings::default()).unwrap(),
+            concat!(
+                "---\n",
+                "date: 2006-01-02\n",
+                "start: 09:00\n",
+                "end: 24:30\n",
+                "---\n",
+                "# 作業\n",
+                "- 09:00-10:15 ABC 1.25 foo: bar\n",
+                "- 10:15-12:00 1.75 日本語\n",
+                "- 12:00-12:01 0.0167 rounding changes the value\n",
+                "- 12:01-24:30 X 1.50 done\n",
+            )
+        );
+    }
+
+    #[test]
+    fn align_columns() {
+        let settings = Settings {
+            align_columns: true,
+            ..Default::default()
+        };
+        let content = concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 09:00\n",
+            "end: 24:30\n",
+            "---\n",
+            "- 09:00-10:15 ABC X1 1.25 foo\n",
+            "- 10:15-12:00 A 1.75 bar\n",
+            "- 12:00-24:30 12.50 baz\n",
+        );
+        assert_eq!(
+            format_with(content, &settings).unwrap(),
+            concat!(
+                "---\n",
+                "date: 2006-01-02\n",
+                "start: 09:00\n",
+                "end: 24:30\n",
+                "---\n",
+                "- 09:00-10:15 ABC X1  1.25 foo\n",
+                "- 10:15-12:00 A       1.75 bar\n",
+                "- 12:00-24:30        12.50 baz\n",
+            )
+        );
+    }
+
+    #[test]
+    fn entry_left_as_it_is() {
+        // An integer duration is read as a code, so the entry is left as it is along with the
+        // front matter time compared with its start time
+        let content = concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 9:00\n",
+            "end: 11:00\n",
+            "---\n",
+            "- 9:00-10:00 1 foo\n",
+            "- 10:00-11:00 1.0 bar\n",
+        );
+        assert_eq!(
+            format_with(content, &Settings::default()).unwrap(),
+            content.replace("1.0 bar", "1.00 bar")
+        );
+    }
+
+    #[test]
+    fn time_format_not_read_back() {
+        // Entries are left as they are rather than written in 12-hour clock which the parser
+        // cannot read back
+        let settings = Settings {
+            time_format: "%-I:%M %p".to_string(),
+            ..Default::default()
+        };
+        let content = concat!(
+            "---\n",
+            "date: 2006-01-02\n",
+            "start: 09:00\n",
+            "end: 10:00\n",
+            "---\n",
+            "- 09:00-10:00 1.0 foo\n",
+        );
+        assert_eq!(format_with(content, &settings), None);
+    }
+
+    #[test]
+    fn formatted() {
+        let content =
+            "---\ndate: 2006-01-02\nstart: 09:00\nend: 10:00\n---\n- 09:00-10:00 1.00 a\n";
+        assert_eq!(format_with(content, &Settings::default()), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! Provides hover information of journals.
+use std::ops::Range;
+use std::time::Duration;
+
+use journalint_parse::settings::Settings;
+
+use crate::journal_info::{format_hmm, format_time_of_day, EntryInfo, JournalInfo};
+
+/// Make a description, in Markdown, of the entry or the front matter at the offset.
+///
+/// The result is paired with the span of the described element.
+pub fn hover(
+    info: &JournalInfo,
+    offset: usize,
+    settings: &Settings,
+) -> Option<(Range<usize>, String)> {
+    if let Some(entry) = info.entry_at(offset) {
+        return Some((entry.span.clone(), describe_entry(info, entry, settings)?));
+    }
+    let fm_span = info.fm_span.as_ref()?;
+    if fm_span.start <= offset && offset < fm_span.end {
+        return Some((fm_span.clone(), describe_front_matter(info, settings)?));
+    }
+    None
+}
+
+fn describe_entry(info: &JournalInfo, entry: &EntryInfo, settings: &Settings) -> Option<String> {
+    let date = info.date?;
+    let (Some(start), Some(end)) = (entry.start, entry.end) else {
+        return None;
+    };
+    let mut lines = vec![format!(
+        "**{} → {}**",
+        format_time_of_day(&start, date, settings),
+        format_time_of_day(&end, date, settings),
+    )];
+    lines.push(String::new());
+    match entry.duration() {
+        Some(duration) => lines.push(format!("- Duration: {}", format_hours(&duration, settings))),
+        None => lines.push("- Duration: (end time precedes start time)".to_string()),
+    }
+    if !entry.codes.is_empty() {
+        let codes: Vec<String> = entry.codes.iter().map(|c| format!("`{c}`")).collect();
+        lines.push(format!("- Codes: {}", codes.join(", ")));
+    }
+    lines.push(format!(
+        "- Running total: {}",
+        format_hours(&entry.running_total, settings)
+    ));
+    Some(lines.join("\n"))
+}
+
+fn describe_front_matter(info: &JournalInfo, settings: &Settings) -> Option<String> {
+    let date = info.date?;
+    let mut lines = vec![format!("**{}**", date.format("%Y-%m-%d"))];
+    lines.push(String::new());
+    if let Some(day_range) = info.day_range() {
+        let span = (day_range.end - day_range.start)
+            .to_std()
+            .unwrap_or_default();
+        lines.push(format!(
+            "- Span: {} → {}, {}",
+            format_time_of_day(&day_range.start, date, settings),
+            format_time_of_day(&day_range.end, date, settings),
+            format_hours(&span, settings),
+        ));
+    }
+    lines.push(format!(
+        "- Logged: {}",
+        format_hours(&info.total(), settings)
+    ));
+    if let Some(gaps) = info.gaps() {
+        if gaps.is_empty() {
+            lines.push("- Gaps: none".to_string());
+        } else {
+            let total: Duration = gaps
+                .iter()
+                .filter_map(|gap| (gap.end - gap.start).to_std().ok())
+                .sum();
+            lines.push(format!("- Gaps: {}", format_hours(&total, settings)));
+            for gap in gaps {
+                lines.push(format!(
+                    "  - {} → {}",
+                    format_time_of_day(&gap.start, date, settings),
+                    format_time_of_day(&gap.end, date, settings),
+                ));
+            }
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+/// Format a duration both in hours and in `h:mm` form.
+fn format_hours(value: &Duration, settings: &Settings) -> String {
+    format!(
+        "{} hours ({})",
+        settings.format_duration(value),
+        format_hmm(value)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::parse::parse;
+
+    const CONTENT: &str = concat!(
+        "---\n",
+        "date: 2006-01-02\n",
+        "start: 09:00\n",
+        "end: 25:00\n",
+        "---\n",
+        "# Today\n",
+        "- 09:00-10:00 A B 1.00 foo\n",
+        "- 23:00-24:30 1.50 bar\n",
+    );
+
+    fn hover_at(pattern: &str) -> Option<String> {
+        let settings = Settings::default();
+        let (journal, _errors) = parse(CONTENT, &settings);
+        let info = JournalInfo::collect(&journal.unwrap()).unwrap();
+        let offset = CONTENT.find(pattern).unwrap();
+        hover(&info, offset, &settings).map(|(_span, text)| text)
+    }
+
+    #[test]
+    fn entry() {
+        assert_eq!(
+            hover_at("- 23:00").unwrap(),
+            concat!(
+                "**23:00 → 00:30 (next day)**\n",
+                "\n",
+                "- Duration: 1.50 hours (1:30)\n",
+                "- Running total: 2.50 hours (2:30)",
+            )
+        );
+    }
+
+    #[test]
+    fn entry_with_codes() {
+        assert_eq!(
+            hover_at("foo").unwrap(),
+            concat!(
+                "**09:00 → 10:00**\n",
+                "\n",
+                "- Duration: 1.00 hours (1:00)\n",
+                "- Codes: `A`, `B`\n",
+                "- Running total: 1.00 hours (1:00)",
+            )
+        );
+    }
+
+    #[test]
+    fn front_matter() {
+        assert_eq!(
+            hover_at("start:").unwrap(),
+            concat!(
+                "**2006-01-02**\n",
+                "\n",
+                "- Span: 09:00 → 01:00 (next day), 16.00 hours (16:00)\n",
+                "- Logged: 2.50 hours (2:30)\n",
+                "- Gaps: 13.50 hours (13:30)\n",
+                "  - 10:00 → 23:00\n",
+                "  - 00:30 (next day) → 01:00 (next day)",
+            )
+        );
+    }
+
+    #[test]
+    fn outside() {
+        assert_eq!(hover_at("# Today"), None);
+    }
+}
@@ -0,0 +1,95 @@
+//! Provides inlay hints showing durations computed from journals.
+use std::ops::Range;
+
+use journalint_parse::settings::Settings;
+
+use crate::journal_info::JournalInfo;
+
+/// Label inserted at an offset in a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlayHint {
+    pub offset: usize,
+    pub label: String,
+    pub tooltip: &'static str,
+}
+
+/// Make inlay hints placed in the span.
+///
+/// Hints are the computed duration after the time range of each entry, the running total at the
+/// end of each entry line and the total of the day next to `end` field in the front matter.
+pub fn inlay_hints(info: &JournalInfo, span: &Range<usize>, settings: &Settings) -> Vec<InlayHint> {
+    let mut hints = Vec::new();
+    if let Some(fm_end_span) = &info.fm_end_span {
+        hints.push(InlayHint {
+            offset: fm_end_span.end,
+            label: format!("Σ {}h", settings.format_duration(&info.total())),
+            tooltip: "Total duration of the entries",
+        });
+    }
+    for entry in &info.entries {
+        if let Some(duration) = entry.duration() {
+            hints.push(InlayHint {
+                offset: entry.time_range_span.end,
+                label: format!("= {}h", settings.format_duration(&duration)),
+                tooltip: "Duration computed from the time range",
+            });
+        }
+        hints.push(InlayHint {
+            offset: entry.span.end,
+            label: format!("Σ {}h", settings.format_duration(&entry.running_total)),
+            tooltip: "Total duration of the entries up to this one",
+        });
+    }
+    hints.retain(|hint| span.start <= hint.offset && hint.offset <= span.end);
+    hints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::parse::parse;
+
+    const CONTENT: &str = concat!(
+        "---\n",
+        "date: 2006-01-02\n",
+        "start: 09:00\n",
+        "end: 24:30\n",
+        "---\n",
+        "- 09:00-10:15 A 1.00 foo\n",
+        "- 23:00-24:30 1.50 bar\n",
+    );
+
+    fn labels(span: Range<usize>) -> Vec<(usize, String)> {
+        let settings = Settings::default();
+        let (journal, _errors) = parse(CONTENT, &settings);
+        let info = JournalInfo::collect(&journal.unwrap()).unwrap();
+        inlay_hints(&info, &span, &settings)
+            .into_iter()
+            .map(|hint| (hint.offset, hint.label))
+            .collect()
+    }
+
+    #[test]
+    fn all() {
+        let offset_of = |s: &str| CONTENT.find(s).unwrap() + s.len();
+        assert_eq!(
+            labels(0..CONTENT.len()),
+            vec![
+                (offset_of("end: 24:30"), "Σ 2.75h".to_string()),
+                (offset_of("- 09:00-10:15"), "= 1.25h".to_string()),
+                (offset_of("foo"), "Σ 1.25h".to_string()),
+                (offset_of("- 23:00-24:30"), "= 1.50h".to_string()),
+                (offset_of("bar"), "Σ 2.75h".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn in_range() {
+        let start = CONTENT.find("- 23:00").unwrap();
+        let labels = labels(start..CONTENT.len());
+        let labels: Vec<&str> = labels.iter().map(|(_, label)| label.as_str()).collect();
+        assert_eq!(labels, vec!["= 1.50h", "Σ 2.75h"]);
+    }
+}
@@ -0,0 +1,59 @@
+//! Provides how journal files are named.
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use journalint_parse::settings::Settings;
+
+/// Get the date embedded in the filename of a journal file (`YYYY-MM-DD.md` by default).
+pub fn journal_file_date(path: &Path, settings: &Settings) -> Option<NaiveDate> {
+    if path.extension().and_then(|s| s.to_str()) != Some("md") {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?;
+    NaiveDate::parse_from_str(stem, &settings.filename_date_format).ok()
+}
+
+/// Check whether the file is named like a journal file (`YYYY-MM-DD.md` by default).
+pub fn is_journal_file(path: &Path, settings: &Settings) -> bool {
+    journal_file_date(path, settings).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest]
+    #[case("2023-05-04.md", true)]
+    #[case("journals/2023/2023-05-04.md", true)]
+    #[case("2023-05-04.txt", false)]
+    #[case("2023-13-04.md", false)]
+    #[case("README.md", false)]
+    fn is_journal_file(#[case] path: &str, #[case] expected: bool) {
+        let settings = Settings::default();
+        assert_eq!(super::is_journal_file(Path::new(path), &settings), expected);
+    }
+
+    #[test]
+    fn is_journal_file_with_custom_date_format() {
+        let settings = Settings {
+            filename_date_format: "%Y%m%d".to_string(),
+            ..Default::default()
+        };
+        assert!(super::is_journal_file(Path::new("20230504.md"), &settings));
+        assert!(!super::is_journal_file(
+            Path::new("2023-05-04.md"),
+            &settings
+        ));
+    }
+
+    #[test]
+    fn journal_file_date() {
+        assert_eq!(
+            super::journal_file_date(Path::new("2023-05-04.md"), &Settings::default()),
+            NaiveDate::from_ymd_opt(2023, 5, 4)
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Provides facts computed from a journal such as time ranges of entries and their totals.
+use std::ops::Range;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use journalint_parse::ast::{walk, Expr, LooseTime, Visitor};
+use journalint_parse::settings::Settings;
+
+use crate::errors::JournalintError;
+
+/// Facts computed from an entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// Span of the whole entry line.
+    pub span: Range<usize>,
+
+    /// Span of the time range, i.e. from the start time to the end time.
+    pub time_range_span: Range<usize>,
+
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+
+    /// End time as written, which may be 24:00 or later.
+    pub end_time: Option<LooseTime>,
+
+    pub codes: Vec<String>,
+    pub activity: Option<String>,
+
+    /// Total duration of the entries up to and including this one.
+    pub running_total: Duration,
+}
+
+impl EntryInfo {
+    /// Get the duration computed from the start time and the end time.
+    ///
+    /// This is `None` if either of them is invalid or the end precedes the start.
+    pub fn duration(&self) -> Option<Duration> {
+        let (Some(start), Some(end)) = (self.start, self.end) else {
+            return None;
+        };
+        (end - start).to_std().ok()
+    }
+}
+
+/// Facts computed from a journal.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JournalInfo {
+    pub date: Option<NaiveDate>,
+
+    /// Span of the front matter.
+    pub fm_span: Option<Range<usize>>,
+    pub fm_start: Option<DateTime<Utc>>,
+    pub fm_end: Option<DateTime<Utc>>,
+
+    /// Span of the value of `end` field in the front matter.
+    pub fm_end_span: Option<Range<usize>>,
+
+    pub entries: Vec<EntryInfo>,
+}
+
+impl JournalInfo {
+    pub fn collect(journal: &Expr) -> Result<Self, JournalintError> {
+        let mut visitor = JournalInfoCollector::default();
+        walk(journal, &mut visitor)?;
+        Ok(visitor.info)
+    }
+
+    /// Get the total duration of all the entries.
+    pub fn total(&self) -> Duration {
+        self.entries
+            .last()
+            .map(|entry| entry.running_total)
+            .unwrap_or_default()
+    }
+
+    /// Get the time range written in the front matter.
+    pub fn day_range(&self) -> Option<Range<DateTime<Utc>>> {
+        let (Some(start), Some(end)) = (self.fm_start, self.fm_end) else {
+            return None;
+        };
+        Some(start..end)
+    }
+
+    /// Get the time ranges in the front matter's time range which no entry covers.
+    ///
+    /// This is `None` if the front matter does not have a valid time range.
+    pub fn gaps(&self) -> Option<Vec<Range<DateTime<Utc>>>> {
+        let day_range = self.day_range()?;
+        let mut ranges: Vec<Range<DateTime<Utc>>> = self
+            .entries
+            .iter()
+            .filter_map(|e| match (e.start, e.end) {
+                (Some(start), Some(end)) if start < end => Some(start..end),
+                _ => None,
+            })
+            .collect();
+        ranges.sort_by_key(|r| r.start);
+
+        let mut gaps = Vec::new();
+        let mut covered_until = day_range.start;
+        for range in ranges {
+            if covered_until < range.start {
+                gaps.push(covered_until..range.start.min(day_range.end));
+            }
+            covered_until = covered_until.max(range.end);
+            if day_range.end <= covered_until {
+                break;
+            }
+        }
+        if covered_until < day_range.end {
+            gaps.push(covered_until..day_range.end);
+        }
+        gaps.retain(|gap| gap.start < gap.end);
+        Some(gaps)
+    }
+
+    /// Find the entry at the offset, including the end of its line.
+    pub fn entry_at(&self, offset: usize) -> Option<&EntryInfo> {
+        self.entries
+            .iter()
+            .find(|e| e.span.start <= offset && offset <= e.span.end)
+    }
+}
+
+#[derive(Default)]
+struct JournalInfoCollector {
+    info: JournalInfo,
+    curr_entry: EntryInfo,
+}
+
+impl JournalInfoCollector {
+    fn to_datetime(&self, value: &LooseTime) -> Option<DateTime<Utc>> {
+        self.info.date.and_then(|d| value.to_datetime(d).ok())
+    }
+}
+
+impl Visitor<JournalintError> for JournalInfoCollector {
+    fn on_visit_fm_date(
+        &mut self,
+        value: &NaiveDate,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.info.date = Some(*value);
+        Ok(())
+    }
+
+    fn on_visit_fm_start(
+        &mut self,
+        value: &LooseTime,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.info.fm_start = self.to_datetime(value);
+        Ok(())
+    }
+
+    fn on_visit_fm_end(
+        &mut self,
+        value: &LooseTime,
+        span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.info.fm_end = self.to_datetime(value);
+        self.info.fm_end_span = Some(span.clone());
+        Ok(())
+    }
+
+    fn on_leave_fm(&mut self, span: &Range<usize>) -> Result<(), JournalintError> {
+        self.info.fm_span = Some(span.clone());
+        Ok(())
+    }
+
+    fn on_visit_entry(&mut self, span: &Range<usize>) -> Result<(), JournalintError> {
+        self.curr_entry = EntryInfo {
+            span: span.clone(),
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    fn on_visit_start_time(
+        &mut self,
+        value: &LooseTime,
+        span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_entry.start = self.to_datetime(value);
+        self.curr_entry.time_range_span = span.clone();
+        Ok(())
+    }
+
+    fn on_visit_end_time(
+        &mut self,
+        value: &LooseTime,
+        span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_entry.end = self.to_datetime(value);
+        self.curr_entry.end_time = Some(value.clone());
+        self.curr_entry.time_range_span.end = span.end;
+        Ok(())
+    }
+
+    fn on_visit_code(&mut self, value: &str, _span: &Range<usize>) -> Result<(), JournalintError> {
+        self.curr_entry.codes.push(value.to_string());
+        Ok(())
+    }
+
+    fn on_visit_activity(
+        &mut self,
+        value: &str,
+        _span: &Range<usize>,
+    ) -> Result<(), JournalintError> {
+        self.curr_entry.activity = Some(value.to_string());
+        Ok(())
+    }
+
+    fn on_leave_entry(&mut self, _span: &Range<usize>) -> Result<(), JournalintError> {
+        let mut entry = std::mem::take(&mut self.curr_entry);
+        entry.running_total = self.info.total() + entry.duration().unwrap_or_default();
+        self.info.entries.push(entry);
+        Ok(())
+    }
+}
+
+/// Split an activity into its prefixes and the rest, e.g. `foo: bar: baz` into `foo`, `bar` and
+/// `baz`.
+pub fn split_activity_prefixes(activity: &str) -> (Vec<&str>, &str) {
+    let mut words: Vec<&str> = activity.split(": ").collect();
+    let body = words.pop().unwrap_or_default();
+    (words, body)
+}
+
+/// Format a duration as hours and minutes such as `1:05`.
+pub fn format_hmm(value: &Duration) -> String {
+    let minutes = value.as_secs() / 60;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Format a time value, noting how many days it is ahead of the journal's date.
+pub fn format_time_of_day(value: &DateTime<Utc>, date: NaiveDate, settings: &Settings) -> String {
+    let time = settings.format_time(value);
+    match (value.date_naive() - date).num_days() {
+        0 => time,
+        1 => format!("{time} (next day)"),
+        n => format!("{time} (+{n} days)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use journalint_parse::parse::parse;
+
+    fn collect(content: &str) -> JournalInfo {
+        let (journal, _errors) = parse(content, &Settings::default());
+        JournalInfo::collect(&journal.unwrap()).unwrap()
+    }
+
+    fn time(s: &str) -> DateTime<Utc> {
+        LooseTime::new(s)
+            .to_datetime(NaiveDate::from_ymd_opt(2006, 1, 2).unwrap())
+            .unwrap()
+    }
+
+    const CONTENT: &str = concat!(
+        "---\n",
+        "date: 2006-01-02\n",
+        "start: 09:00\n",
+        "end: 25:00\n",
+        "---\n",
+        "- 09:00-10:00 A 1.00 foo\n",
+        "- 11:00-10:30 0.50 bar\n",
+        "- 10:30-12:00 1.50 baz\n",
+        "- 23:00-24:30 1.50 qux\n",
+    );
+
+    #[test]
+    fn running_total() {
+        let info = collect(CONTENT);
+        let totals: Vec<u64> = info
+            .entries
+            .iter()
+            .map(|e| e.running_total.as_secs() / 60)
+            .collect();
+        assert_eq!(totals, vec![60, 60, 150, 240]);
+        assert_eq!(info.total(), Duration::from_secs(240 * 60));
+        assert_eq!(info.entries[0].codes, vec!["A".to_string()]);
+        assert_eq!(info.entries[1].duration(), None);
+    }
+
+    #[test]
+    fn gaps() {
+        let info = collect(CONTENT);
+        assert_eq!(
+            info.gaps(),
+            Some(vec![
+                time("10:00")..time("10:30"),
+                time("12:00")..time("23:00"),
+                time("24:30")..time("25:00"),
+            ])
+        );
+    }
+
+    #[test]
+    fn entry_at() {
+        let info = collect(CONTENT);
+        let offset = CONTENT.find("- 10:30").unwrap();
+        assert_eq!(info.entry_at(offset), Some(&info.entries[2]));
+        assert_eq!(info.entry_at(0), None);
+    }
+
+    #[test]
+    fn format_time_of_day() {
+        let date = NaiveDate::from_ymd_opt(2006, 1, 2).unwrap();
+        let settings = Settings::default();
+        assert_eq!(
+            super::format_time_of_day(&time("09:05"), date, &settings),
+            "09:05"
+        );
+        assert_eq!(
+            super::format_time_of_day(&time("24:30"), date, &settings),
+            "00:30 (next day)"
+        );
+        assert_eq!(
+            super::format_time_of_day(&time("48:00"), date, &settings),
+            "00:00 (+2 days)"
+        );
+
+        // Invalid format falls back to the default one rather than panicking
+        let settings = Settings {
+            time_format: "%Q".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            super::format_time_of_day(&time("09:05"), date, &settings),
+            "09:05"
+        );
+    }
+
+    #[rstest::rstest]
+    #[case("foo", vec![], "foo")]
+    #[case("foo: bar: baz", vec!["foo", "bar"], "baz")]
+    #[case("foo:bar", vec![], "foo:bar")]
+    fn split_activity_prefixes(
+        #[case] activity: &str,
+        #[case] prefixes: Vec<&str>,
+        #[case] body: &str,
+    ) {
+        assert_eq!(super::split_activity_prefixes(activity), (prefixes, body));
+    }
+
+    #[test]
+    fn format_hmm() {
+        assert_eq!(super::format_hmm(&Duration::from_secs(3900)), "1:05");
+        assert_eq!(super::format_hmm(&Duration::from_secs(0)), "0:00");
+    }
+}
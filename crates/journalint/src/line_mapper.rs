@@ -1,20 +1,79 @@
 use core::cmp::min;
 
-use lsp_types::Position;
+use lsp_types::{Position, PositionEncodingKind};
+
+/// Unit of the column numbers in positions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    /// Columns are counted in UTF-8 code units (bytes).
+    Utf8,
+
+    /// Columns are counted in UTF-16 code units, which is the default of LSP.
+    Utf16,
+
+    /// Columns are counted in Unicode scalar values (chars).
+    #[default]
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Get the number of code units which represent the char.
+    fn len(&self, ch: char) -> usize {
+        match self {
+            PositionEncoding::Utf8 => ch.len_utf8(),
+            PositionEncoding::Utf16 => ch.len_utf16(),
+            PositionEncoding::Utf32 => 1,
+        }
+    }
+}
+
+impl TryFrom<&PositionEncodingKind> for PositionEncoding {
+    type Error = ();
+
+    fn try_from(value: &PositionEncodingKind) -> Result<Self, Self::Error> {
+        if *value == PositionEncodingKind::UTF8 {
+            Ok(PositionEncoding::Utf8)
+        } else if *value == PositionEncodingKind::UTF16 {
+            Ok(PositionEncoding::Utf16)
+        } else if *value == PositionEncodingKind::UTF32 {
+            Ok(PositionEncoding::Utf32)
+        } else {
+            Err(())
+        }
+    }
+}
+
+impl From<PositionEncoding> for PositionEncodingKind {
+    fn from(value: PositionEncoding) -> Self {
+        match value {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct LineMapper {
     line_offsets: Vec<usize>,
-    content_length: usize,
+    chars: Vec<char>,
+    encoding: PositionEncoding,
 }
 
 impl LineMapper {
+    /// Create a line mapper which counts columns in chars.
     pub fn new(content: &str) -> Self {
+        Self::with_encoding(content, PositionEncoding::Utf32)
+    }
+
+    /// Create a line mapper which counts columns in code units of the encoding.
+    pub fn with_encoding(content: &str, encoding: PositionEncoding) -> Self {
         let mut line_offsets = vec![0];
 
         // char_indices() is based on UTF-8 but chumsky consumes the input as a char
         // stream so we need to count chars.
         let mut iter = content.chars().enumerate();
+        #[allow(clippy::while_let_loop)]
         loop {
             let Some((offset1, ch1)) = iter.next() else {
                 break;
@@ -38,7 +97,8 @@ impl LineMapper {
         assert!(!line_offsets.is_empty());
         Self {
             line_offsets,
-            content_length: content.chars().count(),
+            chars: content.chars().collect(),
+            encoding,
         }
     }
 
@@ -56,29 +116,47 @@ impl LineMapper {
             let line_offset = line_offsets[line_index];
             if offset < line_offset {
                 let prev_line_offset = line_offsets[line_index - 1];
-                return ((line_index - 1) as u32, (offset - prev_line_offset) as u32);
+                return (
+                    (line_index - 1) as u32,
+                    self.count_units(prev_line_offset, offset),
+                );
             }
         }
 
         let final_line_index = line_offsets.len() - 1;
-        let final_line_offset = line_offsets.last().unwrap();
+        let final_line_offset = *line_offsets.last().unwrap();
         (
             final_line_index as u32,
-            (min(offset, self.content_length) - final_line_offset) as u32,
+            self.count_units(final_line_offset, min(offset, self.chars.len())),
         )
     }
 
+    /// Count code units of the chars in the range.
+    fn count_units(&self, start: usize, end: usize) -> u32 {
+        self.chars[start..end]
+            .iter()
+            .map(|ch| self.encoding.len(*ch))
+            .sum::<usize>() as u32
+    }
+
     pub fn offset_from_position(&self, position: Position) -> usize {
         self._offset_from_position(position.line as usize, position.character as usize)
     }
 
     fn _offset_from_position(&self, line: usize, character: usize) -> usize {
         assert!(!self.line_offsets.is_empty());
-        let x = self
+        let mut offset = *self
             .line_offsets
             .get(line)
             .unwrap_or(self.line_offsets.last().unwrap());
-        min(x + character, self.content_length)
+
+        // Advance chars until reaching the column, which may be in the middle of a char
+        let mut units = 0;
+        while units < character && offset < self.chars.len() {
+            units += self.encoding.len(self.chars[offset]);
+            offset += 1;
+        }
+        offset
     }
 
     #[warn(unused_results)]
@@ -100,6 +178,8 @@ impl LineMapper {
 mod tests {
     use super::*;
 
+    use rstest::*;
+
     #[test]
     fn new() {
         let lm = LineMapper::new("a\n亜\r\nc\rd");
@@ -131,4 +211,17 @@ mod tests {
         assert_eq!(lm._offset_from_position(2, 1), 6); // EOS
         assert_eq!(lm._offset_from_position(2, 2), 6); // EOS + 1
     }
+
+    #[rstest]
+    #[case(PositionEncoding::Utf8, [0, 1, 4, 8, 9])]
+    #[case(PositionEncoding::Utf16, [0, 1, 2, 4, 5])]
+    #[case(PositionEncoding::Utf32, [0, 1, 2, 3, 4])]
+    fn with_encoding(#[case] encoding: PositionEncoding, #[case] columns: [u32; 5]) {
+        let lm = LineMapper::with_encoding("x\na亜😀b\n", encoding);
+        for (i, column) in columns.into_iter().enumerate() {
+            let offset = 2 + i;
+            assert_eq!(lm._position_from_offset(offset), (1, column));
+            assert_eq!(lm._offset_from_position(1, column as usize), offset);
+        }
+    }
 }
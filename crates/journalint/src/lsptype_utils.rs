@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use journalint_parse::diagnostic::{Diagnostic, DiagnosticRelatedInformation};
+use journalint_parse::diagnostic::{Diagnostic, DiagnosticRelatedInformation, Severity};
 
 use crate::line_mapper::LineMapper;
 
@@ -17,9 +17,15 @@ impl ToLspDiagnostic for Diagnostic {
             line_mapper.position_from_offset(self.span().start),
             line_mapper.position_from_offset(self.span().end),
         );
+        let severity = match self.severity() {
+            Severity::Hint => lsp_types::DiagnosticSeverity::HINT,
+            Severity::Info => lsp_types::DiagnosticSeverity::INFORMATION,
+            Severity::Warning => lsp_types::DiagnosticSeverity::WARNING,
+            Severity::Error => lsp_types::DiagnosticSeverity::ERROR,
+        };
         lsp_types::Diagnostic::new(
             range,
-            Some(lsp_types::DiagnosticSeverity::WARNING),
+            Some(severity),
             Some(lsp_types::NumberOrString::String(rule)),
             Some(SOURCE_NAME.to_string()),
             self.message().to_owned(),
@@ -1,6 +1,14 @@
 mod cli;
 mod commands;
+mod completion;
+mod config;
+mod document_symbol;
 mod errors;
+mod formatter;
+mod hover;
+mod inlay_hint;
+mod journal_file;
+mod journal_info;
 mod line_mapper;
 mod lsptype_utils;
 mod service;
@@ -48,6 +56,7 @@ mod snapshot_tests {
     use std::sync::Arc;
 
     use journalint_parse::lint::parse_and_lint;
+    use journalint_parse::settings::Settings;
     use lsp_types::Url;
 
     use crate::line_mapper::LineMapper;
@@ -75,7 +84,7 @@ mod snapshot_tests {
             .unwrap();
 
             let line_mapper = Arc::new(LineMapper::new(&content));
-            let (_journal, diagnostics) = parse_and_lint(&fake_url, &content);
+            let (_journal, diagnostics) = parse_and_lint(&fake_url, &content, &Settings::default());
             let diagnostics = diagnostics
                 .iter()
                 .map(|d| d.clone().to_lsptype(&line_mapper))
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use log::debug;
@@ -7,24 +8,44 @@ use log::info;
 use log::warn;
 use lsp_server::Connection;
 use lsp_server::Message;
-use lsp_server::Request;
-use lsp_server::RequestId;
 use lsp_server::Response;
-use lsp_types::ApplyWorkspaceEditParams;
+use lsp_types::CodeAction;
 use lsp_types::CodeActionKind;
 use lsp_types::CodeActionOptions;
+use lsp_types::CodeActionOrCommand;
 use lsp_types::CodeActionProviderCapability;
-use lsp_types::Command;
+use lsp_types::CompletionItem;
+use lsp_types::CompletionItemKind;
+use lsp_types::CompletionOptions;
+use lsp_types::CompletionParams;
+use lsp_types::CompletionTextEdit;
 use lsp_types::DidChangeTextDocumentParams;
 use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
-use lsp_types::ExecuteCommandOptions;
+use lsp_types::DidSaveTextDocumentParams;
+use lsp_types::DocumentFormattingParams;
+use lsp_types::DocumentRangeFormattingParams;
+use lsp_types::DocumentSymbol;
+use lsp_types::DocumentSymbolParams;
+use lsp_types::DocumentSymbolResponse;
+use lsp_types::Hover;
+use lsp_types::HoverContents;
+use lsp_types::HoverParams;
+use lsp_types::HoverProviderCapability;
 use lsp_types::InitializeParams;
+use lsp_types::InitializeResult;
+use lsp_types::InlayHintParams;
+use lsp_types::MarkupContent;
+use lsp_types::MarkupKind;
 use lsp_types::NumberOrString;
+use lsp_types::OneOf;
 use lsp_types::PublishDiagnosticsParams;
 use lsp_types::ServerCapabilities;
+use lsp_types::TextDocumentContentChangeEvent;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
+use lsp_types::TextDocumentSyncOptions;
+use lsp_types::TextDocumentSyncSaveOptions;
 use lsp_types::Url;
 use lsp_types::WorkspaceEdit;
 use strum::IntoEnumIterator;
@@ -33,29 +54,67 @@ use journalint_parse::ast::Expr;
 use journalint_parse::diagnostic::Diagnostic;
 use journalint_parse::lint::parse_and_lint;
 use journalint_parse::rule::Rule;
+use journalint_parse::settings::{is_valid_time_format, Settings};
 
+use crate::commands::fix_all;
+use crate::commands::get_default_autofix;
 use crate::commands::AutofixCommand;
 use crate::commands::Command as _;
+use crate::completion::{complete, CompletionKind, Vocabulary, WorkspaceJournals};
+use crate::config::resolve_settings;
+use crate::document_symbol::{document_symbols, Symbol, SymbolKind};
 use crate::errors::JournalintError;
-use crate::line_mapper::LineMapper;
+use crate::formatter::format;
+use crate::hover::hover;
+use crate::inlay_hint::inlay_hints;
+use crate::journal_info::JournalInfo;
+use crate::line_mapper::{LineMapper, PositionEncoding};
 use crate::lsptype_utils::ToLspDiagnostic;
+use crate::text_edit::TextEdit;
 
-const E_UNKNOWN_COMMAND: i32 = 1;
-const E_INVALID_ARGUMENTS: i32 = 2;
+/// Kind of the code action which fixes all the fixable violations in a document.
+const SOURCE_FIX_ALL_JOURNALINT: &str = "source.fixAll.journalint";
 
 /// State of the journalint language server.
 #[derive(Default)]
 pub struct ServerState {
     document_states: HashMap<Url, DocumentState>,
-    sent_requests: Vec<Request>,
-    msgid_counter: u16,
+
+    /// Settings used for documents without a configuration file.
+    default_settings: Settings,
+
+    /// Unit of columns in positions negotiated with the client.
+    position_encoding: PositionEncoding,
+
+    /// Root directories of the workspace folders opened in the client.
+    workspace_roots: Vec<PathBuf>,
+
+    /// Journal files read for completion, per workspace root (or per directory of documents out
+    /// of the workspace folders).
+    workspace_journals: HashMap<PathBuf, WorkspaceJournals>,
 }
 
 impl ServerState {
-    /// Generates numeric ID for next request message.
-    fn next_request_id(&mut self) -> RequestId {
-        self.msgid_counter = self.msgid_counter.wrapping_add(1);
-        RequestId::from(i32::from(self.msgid_counter))
+    fn new(default_settings: Settings, position_encoding: PositionEncoding) -> Self {
+        Self {
+            default_settings,
+            position_encoding,
+            ..Default::default()
+        }
+    }
+
+    /// Determine settings for the specified document.
+    ///
+    /// A configuration file is searched from the directory of the document, and the settings
+    /// given as `initializationOptions` are used if none was found.
+    fn settings_for(&self, url: &Url) -> Settings {
+        let Ok(path) = url.to_file_path() else {
+            return self.default_settings.clone();
+        };
+        resolve_settings(None, &path, &self.default_settings).unwrap_or_else(|e| {
+            error!("{}", e);
+            self.default_settings.clone()
+        })
     }
 
     // Get state data for the specified document.
@@ -74,23 +133,65 @@ impl ServerState {
     fn remove_document_state(&mut self, url: &Url) -> Option<DocumentState> {
         self.document_states.remove(url)
     }
+
+    /// Get the journal files in the workspace folder containing a document, reading them unless
+    /// cached.
+    ///
+    /// Only the directory of the document is read if it is not in any workspace folder, so as
+    /// not to search a large directory tree such as the home directory.
+    fn workspace_journals(&mut self, path: &Path, settings: &Settings) -> &WorkspaceJournals {
+        let root = self
+            .workspace_roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count());
+        let (dir, recursive) = match root {
+            Some(root) => (root.clone(), true),
+            None => (path.parent().unwrap_or(path).to_path_buf(), false),
+        };
+        self.workspace_journals
+            .entry(dir)
+            .or_insert_with_key(|dir| WorkspaceJournals::read(dir, recursive, settings))
+    }
+
+    /// Forget the journal files cached for the workspace folder (or directory) of a document to
+    /// read them again.
+    fn invalidate_workspace_journals(&mut self, url: &Url) {
+        if let Ok(path) = url.to_file_path() {
+            self.workspace_journals
+                .retain(|dir, _| !path.starts_with(dir));
+        }
+    }
 }
 
 /// State data associated with a document.
 #[derive(Default)]
 pub struct DocumentState {
+    content: String,
     line_mapper: Arc<LineMapper>,
     ast_root: Option<Expr>,
+    settings: Settings,
 }
 
 impl DocumentState {
-    pub fn new(line_mapper: Arc<LineMapper>, ast_root: Option<Expr>) -> Self {
+    pub fn new(
+        content: String,
+        line_mapper: Arc<LineMapper>,
+        ast_root: Option<Expr>,
+        settings: Settings,
+    ) -> Self {
         Self {
+            content,
             line_mapper,
             ast_root,
+            settings,
         }
     }
 
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
     pub fn line_mapper(&self) -> Arc<LineMapper> {
         self.line_mapper.clone()
     }
@@ -98,6 +199,10 @@ impl DocumentState {
     pub fn ast_root(&self) -> Option<&Expr> {
         self.ast_root.as_ref()
     }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
 }
 
 pub fn main() -> Result<(), JournalintError> {
@@ -106,40 +211,111 @@ pub fn main() -> Result<(), JournalintError> {
     // Initialize connection
     let (conn, io_threads) = Connection::stdio();
 
+    // Receive the client's capabilities first to determine the server's ones
+    let (init_id, init_params) = conn.initialize_start()?;
+    let init_params: InitializeParams = serde_json::from_value(init_params)?;
+    let position_encoding = negotiate_position_encoding(&init_params);
+
     // Initialize server
-    let server_capabilities = serde_json::to_value(ServerCapabilities {
-        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+    let server_capabilities = ServerCapabilities {
+        position_encoding: Some(position_encoding.into()),
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::INCREMENTAL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
         code_action_provider: Some(CodeActionProviderCapability::Options(CodeActionOptions {
-            code_action_kinds: Some(vec![CodeActionKind::new("quickfix")]),
+            code_action_kinds: Some(vec![
+                CodeActionKind::QUICKFIX,
+                CodeActionKind::new(SOURCE_FIX_ALL_JOURNALINT),
+            ]),
             work_done_progress_options: lsp_types::WorkDoneProgressOptions {
                 work_done_progress: Some(false),
             },
             resolve_provider: Some(false),
         })),
-        execute_command_provider: Some(ExecuteCommandOptions {
-            commands: AutofixCommand::iter()
-                .map(|cmd| cmd.id().to_string())
-                .collect::<Vec<String>>(),
-            work_done_progress_options: lsp_types::WorkDoneProgressOptions {
-                work_done_progress: Some(false),
-            },
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![" ".to_string()]),
+            ..Default::default()
         }),
+        inlay_hint_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        document_formatting_provider: Some(OneOf::Left(true)),
+        document_range_formatting_provider: Some(OneOf::Left(true)),
         ..Default::default()
+    };
+    let initialize_result = serde_json::to_value(InitializeResult {
+        capabilities: server_capabilities,
+        server_info: None,
     })
     .unwrap();
-    let init_params = conn.initialize(server_capabilities)?;
-    let init_params: InitializeParams = serde_json::from_value(init_params)?;
+    conn.initialize_finish(init_id, initialize_result)?;
 
     // Run the message loop
-    message_loop(&conn, &init_params);
+    message_loop(&conn, &init_params, position_encoding);
     io_threads.join()?;
 
     info!("Shutting down journalint language server.");
     Ok(())
 }
 
-fn message_loop(conn: &Connection, _init_params: &InitializeParams) {
-    let mut state = ServerState::default();
+/// Choose the position encoding to use.
+///
+/// The first one supported by journalint among the ones listed by the client is used, and
+/// UTF-16 is used if none was listed as LSP requires.
+fn negotiate_position_encoding(init_params: &InitializeParams) -> PositionEncoding {
+    init_params
+        .capabilities
+        .general
+        .as_ref()
+        .and_then(|general| general.position_encodings.as_ref())
+        .and_then(|encodings| {
+            encodings
+                .iter()
+                .find_map(|kind| PositionEncoding::try_from(kind).ok())
+        })
+        .unwrap_or(PositionEncoding::Utf16)
+}
+
+/// Determine root directories of the workspace folders, or of the root URI of old clients.
+fn workspace_roots(init_params: &InitializeParams) -> Vec<PathBuf> {
+    let urls: Vec<Url> = match &init_params.workspace_folders {
+        Some(folders) => folders.iter().map(|f| f.uri.clone()).collect(),
+        None => init_params.root_uri.iter().cloned().collect(),
+    };
+    urls.iter()
+        .filter_map(|url| url.to_file_path().ok())
+        .collect()
+}
+
+fn message_loop(
+    conn: &Connection,
+    init_params: &InitializeParams,
+    position_encoding: PositionEncoding,
+) {
+    let default_settings = match &init_params.initialization_options {
+        Some(options) => match serde_json::from_value::<Settings>(options.clone()) {
+            Ok(settings) if !is_valid_time_format(&settings.time_format) => {
+                error!(
+                    "Invalid initializationOptions: invalid time-format {:?}",
+                    settings.time_format
+                );
+                Settings::default()
+            }
+            Ok(settings) => settings,
+            Err(e) => {
+                error!("Invalid initializationOptions: {}", e);
+                Settings::default()
+            }
+        },
+        None => Settings::default(),
+    };
+    let mut state = ServerState::new(default_settings, position_encoding);
+    state.workspace_roots = workspace_roots(init_params);
 
     // Receive messages until the connection was closed
     for msg in &conn.receiver {
@@ -156,6 +332,11 @@ fn message_loop(conn: &Connection, _init_params: &InitializeParams) {
                     if let Err(e) = on_text_document_did_change(&mut state, conn, msg) {
                         error!("{}", e);
                     }
+                } else if msg.method == "textDocument/didSave" {
+                    // User saved a document. Read it again on completion in other documents.
+                    if let Err(e) = on_text_document_did_save(&mut state, msg) {
+                        error!("{}", e);
+                    }
                 } else if msg.method == "textDocument/didClose" {
                     // User closed a document. Clear diagnostics for the document.
                     if let Err(e) = on_text_document_did_close(&mut state, conn, msg) {
@@ -171,9 +352,34 @@ fn message_loop(conn: &Connection, _init_params: &InitializeParams) {
                     if let Err(e) = on_text_document_code_action(&mut state, conn, msg) {
                         error!("{}", e);
                     }
-                } else if msg.method == "workspace/executeCommand" {
-                    // User (client) requested to execute a command.
-                    if let Err(e) = on_workspace_execute_command(&mut state, conn, msg) {
+                } else if msg.method == "textDocument/hover" {
+                    // User (client) requested information about the element at a location.
+                    if let Err(e) = on_text_document_hover(&state, conn, msg) {
+                        error!("{}", e);
+                    }
+                } else if msg.method == "textDocument/completion" {
+                    // User (client) requested candidates of what is being typed.
+                    if let Err(e) = on_text_document_completion(&mut state, conn, msg) {
+                        error!("{}", e);
+                    }
+                } else if msg.method == "textDocument/documentSymbol" {
+                    // User (client) requested the outline of a document.
+                    if let Err(e) = on_text_document_document_symbol(&state, conn, msg) {
+                        error!("{}", e);
+                    }
+                } else if msg.method == "textDocument/inlayHint" {
+                    // User (client) requested inlay hints to show in a range.
+                    if let Err(e) = on_text_document_inlay_hint(&state, conn, msg) {
+                        error!("{}", e);
+                    }
+                } else if msg.method == "textDocument/formatting" {
+                    // User (client) requested to format a document.
+                    if let Err(e) = on_text_document_formatting(&state, conn, msg) {
+                        error!("{}", e);
+                    }
+                } else if msg.method == "textDocument/rangeFormatting" {
+                    // User (client) requested to format a range of a document.
+                    if let Err(e) = on_text_document_range_formatting(&state, conn, msg) {
                         error!("{}", e);
                     }
                 } else {
@@ -183,20 +389,6 @@ fn message_loop(conn: &Connection, _init_params: &InitializeParams) {
             }
 
             Message::Response(msg) => {
-                // Find the request matching this response
-                let index = state.sent_requests.iter().enumerate().find_map(|(i, req)| {
-                    if req.id == msg.id {
-                        Some(i)
-                    } else {
-                        None
-                    }
-                });
-
-                // Forget the matching request if found
-                if let Some(index) = index {
-                    state.sent_requests.swap_remove(index);
-                }
-
                 // Write log message
                 if let Some(result) = &msg.result {
                     info!("[R:{}] {:?}", msg.id, result);
@@ -221,15 +413,21 @@ fn on_text_document_did_open(
     let version = None;
 
     // Parse and lint
-    let line_mapper = Arc::new(LineMapper::new(content));
-    let (journal, diagnostics) = parse_and_lint(&uri, content);
+    let settings = state.settings_for(&uri);
+    let line_mapper = Arc::new(LineMapper::with_encoding(content, state.position_encoding));
+    let (journal, diagnostics) = parse_and_lint(&uri, content, &settings);
 
     // Publish diagnostics
     publish_diagnostics(conn, &uri, &line_mapper, &diagnostics, version)?;
 
     // Update (replace) state data for the document
-    state.set_document_state(&uri, DocumentState::new(line_mapper, journal));
+    state.set_document_state(
+        &uri,
+        DocumentState::new(content.to_string(), line_mapper, journal, settings),
+    );
 
+    // The document may be a new journal file, which other documents complete words from
+    state.invalidate_workspace_journals(&uri);
     Ok(())
 }
 
@@ -241,21 +439,60 @@ fn on_text_document_did_change(
     // Deserialize parameters
     let params: DidChangeTextDocumentParams = serde_json::from_value(msg.params)?;
     let uri = params.text_document.uri;
-    let content = params
-        .content_changes
-        .last()
-        .map_or("", |e| e.text.as_str());
     let version = Some(params.text_document.version);
 
+    // Apply the changes to the text we have
+    let mut content = state.document_state(&uri)?.content().to_string();
+    apply_content_changes(
+        &mut content,
+        &params.content_changes,
+        state.position_encoding,
+    );
+    let content = content.as_str();
+
     // Parse and lint
-    let line_mapper = Arc::new(LineMapper::new(content));
-    let (journal, diagnostics) = parse_and_lint(&uri, content);
+    let settings = state.settings_for(&uri);
+    let line_mapper = Arc::new(LineMapper::with_encoding(content, state.position_encoding));
+    let (journal, diagnostics) = parse_and_lint(&uri, content, &settings);
 
     // Publish diagnostics
     publish_diagnostics(conn, &uri, &line_mapper, &diagnostics, version)?;
 
     // Update (replace) state data for the document
-    state.set_document_state(&uri, DocumentState::new(line_mapper, journal));
+    state.set_document_state(
+        &uri,
+        DocumentState::new(content.to_string(), line_mapper, journal, settings),
+    );
+    Ok(())
+}
+
+/// Apply changes of a document in order.
+///
+/// A change replaces the whole text if it has no range.
+fn apply_content_changes(
+    content: &mut String,
+    changes: &[TextDocumentContentChangeEvent],
+    position_encoding: PositionEncoding,
+) {
+    for change in changes {
+        match &change.range {
+            Some(range) => {
+                // Positions of a change are based on the text after the preceding changes
+                let line_mapper = LineMapper::with_encoding(content, position_encoding);
+                let span = line_mapper.lsp_range_to_span(range);
+                TextEdit::new(span, change.text.clone()).apply(content);
+            }
+            None => change.text.clone_into(content),
+        }
+    }
+}
+
+fn on_text_document_did_save(
+    state: &mut ServerState,
+    msg: lsp_server::Notification,
+) -> Result<(), JournalintError> {
+    let params: DidSaveTextDocumentParams = serde_json::from_value(msg.params)?;
+    state.invalidate_workspace_journals(&params.text_document.uri);
     Ok(())
 }
 
@@ -282,111 +519,401 @@ fn on_text_document_did_close(
 }
 
 fn on_text_document_code_action(
-    _state: &mut ServerState,
+    state: &mut ServerState,
     conn: &Connection,
     msg: lsp_server::Request,
 ) -> Result<(), JournalintError> {
     let params: lsp_types::CodeActionParams = serde_json::from_value(msg.params)?;
     let uri = &params.text_document.uri;
-    let position = params.range;
     let diagnostics = &params.context.diagnostics;
-    let mut all_commands: Vec<Command> = Vec::new();
-    for d in diagnostics {
-        // Determine which rule is behind the diagnosed error or warning.
-        let code = &d.code;
-        let Some(NumberOrString::String(code)) = code else {
-            continue; // journalint's rule name is string
-        };
-        let Ok(rule) = str::parse::<Rule>(code) else {
-            continue; // not a known journalint's rule name
-        };
-
-        // List up all available code actions for the rule violation.
-        let mut commands: Vec<Command> = AutofixCommand::iter()
-            .filter(|cmd| cmd.can_fix(&rule))
-            .map(|cmd| {
-                lsp_types::Command::new(
-                    cmd.title().to_string(),
-                    cmd.id().to_string(),
-                    Some(vec![
-                        serde_json::to_value(uri).unwrap(),
-                        serde_json::to_value(position).unwrap(),
-                    ]),
-                )
-            })
-            .collect();
-        all_commands.append(&mut commands);
-    }
+    let only = params.context.only.as_deref();
+    let all_actions = code_actions(state, uri, diagnostics, only)?;
     conn.sender.send(Message::Response(Response::new_ok(
         msg.id.clone(),
-        all_commands,
+        all_actions,
     )))?;
     Ok(())
 }
 
-fn on_workspace_execute_command(
-    state: &mut ServerState,
+/// Make code actions of the requested kinds for the violations.
+fn code_actions(
+    state: &ServerState,
+    uri: &Url,
+    diagnostics: &[lsp_types::Diagnostic],
+    only: Option<&[CodeActionKind]>,
+) -> Result<Vec<CodeActionOrCommand>, JournalintError> {
+    let mut all_actions: Vec<CodeActionOrCommand> = Vec::new();
+
+    // List up quick fixes for the violations
+    if is_requested_kind(only, &CodeActionKind::QUICKFIX) {
+        for d in diagnostics {
+            for action in quick_fix_code_actions(state, uri, d)? {
+                all_actions.push(CodeActionOrCommand::CodeAction(action));
+            }
+        }
+    }
+
+    // Offer fixing all the fixable violations at once (e.g. on save), only if explicitly
+    // requested since it runs all the fixes on every request
+    if only.is_some() && is_requested_kind(only, &CodeActionKind::new(SOURCE_FIX_ALL_JOURNALINT)) {
+        if let Some(action) = fix_all_code_action(state, uri)? {
+            all_actions.push(CodeActionOrCommand::CodeAction(action));
+        }
+    }
+    Ok(all_actions)
+}
+
+/// Make quick fix code actions for a violation, each of which carries the edit to apply.
+///
+/// The one which `--fix` would apply is marked as the preferred one.
+fn quick_fix_code_actions(
+    state: &ServerState,
+    url: &Url,
+    diagnostic: &lsp_types::Diagnostic,
+) -> Result<Vec<CodeAction>, JournalintError> {
+    // Determine which rule is behind the diagnosed error or warning.
+    let Some(NumberOrString::String(code)) = &diagnostic.code else {
+        return Ok(vec![]); // journalint's rule name is string
+    };
+    let Ok(rule) = str::parse::<Rule>(code) else {
+        return Ok(vec![]); // not a known journalint's rule name
+    };
+
+    let doc_state = state.document_state(url)?;
+    let Some(ast_root) = doc_state.ast_root() else {
+        return Ok(vec![]);
+    };
+    let line_mapper = doc_state.line_mapper();
+    let span = line_mapper.lsp_range_to_span(&diagnostic.range);
+    let preferred = get_default_autofix(&rule);
+
+    // List up all available fixes for the rule violation.
+    let mut actions = Vec::new();
+    for command in AutofixCommand::iter().filter(|cmd| cmd.can_fix(&rule)) {
+        let edit = match command.execute(
+            url,
+            doc_state.content(),
+            ast_root,
+            &span,
+            doc_state.settings(),
+        ) {
+            Ok(Some(edit)) => edit,
+            Ok(None) => continue, // nothing to change
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        let text_edit = edit.to_lsp_type(&line_mapper);
+        actions.push(CodeAction {
+            title: command.title().to_string(),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit::new(HashMap::from([(
+                url.clone(),
+                vec![text_edit],
+            )]))),
+            is_preferred: Some(preferred == Some(command)),
+            ..Default::default()
+        });
+    }
+    Ok(actions)
+}
+
+/// Check whether a kind of code actions is requested by the `only` filter.
+///
+/// Kinds are hierarchical so `source` requests `source.fixAll.journalint` for example.
+fn is_requested_kind(only: Option<&[CodeActionKind]>, kind: &CodeActionKind) -> bool {
+    let Some(only) = only else {
+        return true;
+    };
+    only.iter().any(|requested| {
+        let requested = requested.as_str();
+        kind.as_str() == requested
+            || kind
+                .as_str()
+                .strip_prefix(requested)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Make a code action which fixes all the fixable violations in the document.
+///
+/// This returns `None` if there is nothing to fix.
+fn fix_all_code_action(
+    state: &ServerState,
+    url: &Url,
+) -> Result<Option<CodeAction>, JournalintError> {
+    let doc_state = state.document_state(url)?;
+    let content = doc_state.content();
+    let mut buffer = content.to_string();
+    let (num_fixed, _) = fix_all(url, &mut buffer, doc_state.settings())?;
+    if num_fixed == 0 {
+        return Ok(None);
+    }
+
+    // Replace the whole document with the fixed one
+    let span = 0..content.chars().count();
+    let text_edit = TextEdit::new(span, buffer).to_lsp_type(&doc_state.line_mapper());
+    Ok(Some(CodeAction {
+        title: "Fix all auto-fixable problems".to_string(),
+        kind: Some(CodeActionKind::new(SOURCE_FIX_ALL_JOURNALINT)),
+        edit: Some(WorkspaceEdit::new(HashMap::from([(
+            url.clone(),
+            vec![text_edit],
+        )]))),
+        ..Default::default()
+    }))
+}
+
+fn on_text_document_hover(
+    state: &ServerState,
     conn: &Connection,
     msg: lsp_server::Request,
 ) -> Result<(), JournalintError> {
-    let params: lsp_types::ExecuteCommandParams = serde_json::from_value(msg.params)?;
-
-    // Dispatch the requested command
-    let Some(command) = AutofixCommand::iter().find(|cmd| cmd.id() == params.command.as_str())
-    else {
-        let err = format!("Unknown command: {}", params.command.as_str());
-        conn.sender.send(Message::Response(Response::new_err(
-            msg.id.clone(),
-            E_UNKNOWN_COMMAND,
-            err.clone(),
-        )))?;
-        return Err(JournalintError::UnknownCommand(err));
+    let params: HoverParams = serde_json::from_value(msg.params)?;
+    let position = params.text_document_position_params;
+    let hover = hover_at(state, &position.text_document.uri, position.position)?;
+    conn.sender
+        .send(Message::Response(Response::new_ok(msg.id, hover)))?;
+    Ok(())
+}
+
+/// Make hover information of the entry or the front matter at the position.
+fn hover_at(
+    state: &ServerState,
+    url: &Url,
+    position: lsp_types::Position,
+) -> Result<Option<Hover>, JournalintError> {
+    let doc_state = state.document_state(url)?;
+    let Some(ast_root) = doc_state.ast_root() else {
+        return Ok(None);
+    };
+    let line_mapper = doc_state.line_mapper();
+    let info = JournalInfo::collect(ast_root)?;
+    let offset = line_mapper.offset_from_position(position);
+    let Some((span, value)) = hover(&info, offset, doc_state.settings()) else {
+        return Ok(None);
     };
+    Ok(Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(line_mapper.span_to_lsp_range(&span)),
+    }))
+}
 
-    // Extract command parameters from the message
-    if params.arguments.len() != 2 {
-        let err = format!(
-            "Number of command parameters is expected to be 2 but was {}",
-            params.arguments.len()
-        );
-        conn.sender.send(Message::Response(Response::new_err(
-            msg.id.clone(),
-            E_INVALID_ARGUMENTS,
-            err.clone(),
-        )))?;
-        return Err(JournalintError::UnexpectedArguments(err));
+fn on_text_document_completion(
+    state: &mut ServerState,
+    conn: &Connection,
+    msg: lsp_server::Request,
+) -> Result<(), JournalintError> {
+    let params: CompletionParams = serde_json::from_value(msg.params)?;
+    let position = params.text_document_position;
+    let items = completion_items(state, &position.text_document.uri, position.position)?;
+    conn.sender
+        .send(Message::Response(Response::new_ok(msg.id, items)))?;
+    Ok(())
+}
+
+/// Make completion items for the text being typed at the position.
+///
+/// Codes and activity prefixes are collected from the document and the other journal files in
+/// the workspace.
+fn completion_items(
+    state: &mut ServerState,
+    url: &Url,
+    position: lsp_types::Position,
+) -> Result<Vec<CompletionItem>, JournalintError> {
+    let mut vocabulary = Vocabulary::default();
+    if let Ok(path) = url.to_file_path() {
+        let settings = state.document_state(url)?.settings().clone();
+        vocabulary.add_workspace_journals(&path, state.workspace_journals(&path, &settings));
     }
-    let url: Url = serde_json::from_value(params.arguments[0].clone())?;
-    let selected_range: lsp_types::Range = serde_json::from_value(params.arguments[1].clone())?;
 
-    // Execute the command
-    let doc_state = state.document_state(&url)?;
+    let doc_state = state.document_state(url)?;
     let line_mapper = doc_state.line_mapper();
-    let ast_root = doc_state.ast_root().ok_or_else(|| {
-        JournalintError::UnexpectedError(format!("No AST available for the document: {url}"))
-    })?;
-    let selected_span = line_mapper.lsp_range_to_span(&selected_range);
-    let Some(edit) = command.execute(&url, ast_root, &selected_span)? else {
-        return Ok(()); // Do nothing if command does not change the document
+    let info = match doc_state.ast_root() {
+        Some(ast_root) => JournalInfo::collect(ast_root)?,
+        None => JournalInfo::default(), // still complete words found in other files
+    };
+    let line_start = line_mapper.offset_from_position(lsp_types::Position::new(position.line, 0));
+    let cursor = line_mapper.offset_from_position(position);
+    let line: String = doc_state
+        .content()
+        .chars()
+        .skip(line_start)
+        .take(cursor - line_start)
+        .collect();
+
+    vocabulary.add_document(&info, cursor);
+
+    let completions = complete(&line, line_start, &info, &vocabulary, doc_state.settings());
+    let items = completions
+        .into_iter()
+        .enumerate()
+        .map(|(i, completion)| {
+            let detail = match completion.kind {
+                CompletionKind::StartTime => "End time of the previous entry",
+                CompletionKind::Code => "Code",
+                CompletionKind::ActivityPrefix => "Activity prefix",
+            };
+            CompletionItem {
+                label: completion.label,
+                kind: Some(CompletionItemKind::VALUE),
+                detail: Some(detail.to_string()),
+                // Keep the order by frequency and recency
+                sort_text: Some(format!("{i:04}")),
+                text_edit: Some(CompletionTextEdit::Edit(lsp_types::TextEdit::new(
+                    line_mapper.span_to_lsp_range(&completion.span),
+                    completion.new_text,
+                ))),
+                ..Default::default()
+            }
+        })
+        .collect();
+    Ok(items)
+}
+
+fn on_text_document_document_symbol(
+    state: &ServerState,
+    conn: &Connection,
+    msg: lsp_server::Request,
+) -> Result<(), JournalintError> {
+    let params: DocumentSymbolParams = serde_json::from_value(msg.params)?;
+    let doc_state = state.document_state(&params.text_document.uri)?;
+    let symbols = match doc_state.ast_root() {
+        Some(ast_root) => document_symbols(ast_root)?,
+        None => vec![],
+    };
+    let line_mapper = doc_state.line_mapper();
+    let symbols = symbols
+        .into_iter()
+        .map(|symbol| to_lsp_document_symbol(symbol, &line_mapper))
+        .collect();
+    conn.sender.send(Message::Response(Response::new_ok(
+        msg.id,
+        DocumentSymbolResponse::Nested(symbols),
+    )))?;
+    Ok(())
+}
+
+fn to_lsp_document_symbol(symbol: Symbol, line_mapper: &LineMapper) -> DocumentSymbol {
+    let kind = match symbol.kind {
+        SymbolKind::FrontMatter => lsp_types::SymbolKind::OBJECT,
+        SymbolKind::Heading => lsp_types::SymbolKind::STRING,
+        SymbolKind::Entry => lsp_types::SymbolKind::EVENT,
     };
-    let text_edit = edit.to_lsp_type(&line_mapper);
-    let workspace_edit = WorkspaceEdit::new(HashMap::from([(url.clone(), vec![text_edit])]));
-
-    // Request the changes to be executed to the client
-    let request_id = state.next_request_id();
-    info!("[S:{}] textDocument/applyEdit", request_id);
-    let params = ApplyWorkspaceEditParams {
-        label: Some(command.title().to_string()),
-        edit: workspace_edit,
+    #[allow(deprecated)] // `deprecated` field is deprecated in favor of `tags`
+    DocumentSymbol {
+        name: symbol.name,
+        detail: symbol.detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: line_mapper.span_to_lsp_range(&symbol.span),
+        selection_range: line_mapper.span_to_lsp_range(&symbol.selection_span),
+        children: Some(
+            symbol
+                .children
+                .into_iter()
+                .map(|child| to_lsp_document_symbol(child, line_mapper))
+                .collect(),
+        ),
+    }
+}
+
+fn on_text_document_inlay_hint(
+    state: &ServerState,
+    conn: &Connection,
+    msg: lsp_server::Request,
+) -> Result<(), JournalintError> {
+    let params: InlayHintParams = serde_json::from_value(msg.params)?;
+    let hints = inlay_hints_in(state, &params.text_document.uri, &params.range)?;
+    conn.sender
+        .send(Message::Response(Response::new_ok(msg.id, hints)))?;
+    Ok(())
+}
+
+/// Make inlay hints placed in the range.
+fn inlay_hints_in(
+    state: &ServerState,
+    url: &Url,
+    range: &lsp_types::Range,
+) -> Result<Vec<lsp_types::InlayHint>, JournalintError> {
+    let doc_state = state.document_state(url)?;
+    let Some(ast_root) = doc_state.ast_root() else {
+        return Ok(vec![]);
     };
-    let request = Request::new(request_id, "workspace/applyEdit".to_string(), params);
-    conn.sender.send(Message::Request(request.clone()))?;
+    let line_mapper = doc_state.line_mapper();
+    let info = JournalInfo::collect(ast_root)?;
+    let span = line_mapper.lsp_range_to_span(range);
+    let hints = inlay_hints(&info, &span, doc_state.settings())
+        .into_iter()
+        .map(|hint| lsp_types::InlayHint {
+            position: line_mapper.position_from_offset(hint.offset),
+            label: lsp_types::InlayHintLabel::String(hint.label),
+            kind: None,
+            text_edits: None,
+            tooltip: Some(lsp_types::InlayHintTooltip::String(
+                hint.tooltip.to_string(),
+            )),
+            padding_left: Some(true),
+            padding_right: None,
+            data: None,
+        })
+        .collect();
+    Ok(hints)
+}
 
-    // Remember the request until a corresponding response arrives
-    state.sent_requests.push(request);
+fn on_text_document_formatting(
+    state: &ServerState,
+    conn: &Connection,
+    msg: lsp_server::Request,
+) -> Result<(), JournalintError> {
+    let params: DocumentFormattingParams = serde_json::from_value(msg.params)?;
+    let edits = formatting_edits(state, &params.text_document.uri, None)?;
+    conn.sender
+        .send(Message::Response(Response::new_ok(msg.id, edits)))?;
+    Ok(())
+}
+
+fn on_text_document_range_formatting(
+    state: &ServerState,
+    conn: &Connection,
+    msg: lsp_server::Request,
+) -> Result<(), JournalintError> {
+    let params: DocumentRangeFormattingParams = serde_json::from_value(msg.params)?;
+    let edits = formatting_edits(state, &params.text_document.uri, Some(&params.range))?;
+    conn.sender
+        .send(Message::Response(Response::new_ok(msg.id, edits)))?;
     Ok(())
 }
 
+/// Make edits which format a document, or only the lines overlapping the range if given.
+fn formatting_edits(
+    state: &ServerState,
+    url: &Url,
+    range: Option<&lsp_types::Range>,
+) -> Result<Vec<lsp_types::TextEdit>, JournalintError> {
+    let doc_state = state.document_state(url)?;
+    let Some(ast_root) = doc_state.ast_root() else {
+        return Ok(vec![]);
+    };
+    let line_mapper = doc_state.line_mapper();
+    let span = range.map(|range| line_mapper.lsp_range_to_span(range));
+    let edits = format(doc_state.content(), ast_root, doc_state.settings())?
+        .into_iter()
+        .filter(|edit| match &span {
+            Some(span) => edit.span().start <= span.end && span.start <= edit.span().end,
+            None => true,
+        })
+        .map(|edit| edit.to_lsp_type(&line_mapper))
+        .collect();
+    Ok(edits)
+}
+
 #[warn(unused_results)]
 fn publish_diagnostics(
     conn: &Connection,
@@ -413,3 +940,293 @@ fn publish_diagnostics(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest]
+    #[case(None, PositionEncoding::Utf16)]
+    #[case(Some(vec![]), PositionEncoding::Utf16)]
+    #[case(Some(vec!["utf-32", "utf-16"]), PositionEncoding::Utf32)]
+    #[case(Some(vec!["utf-7", "utf-8"]), PositionEncoding::Utf8)]
+    fn negotiate_position_encoding(
+        #[case] encodings: Option<Vec<&'static str>>,
+        #[case] want: PositionEncoding,
+    ) {
+        let init_params = InitializeParams {
+            capabilities: lsp_types::ClientCapabilities {
+                general: Some(lsp_types::GeneralClientCapabilities {
+                    position_encodings: encodings.map(|v| {
+                        v.into_iter()
+                            .map(lsp_types::PositionEncodingKind::from)
+                            .collect()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(super::negotiate_position_encoding(&init_params), want);
+    }
+
+    #[rstest]
+    #[case(None, true)]
+    #[case(Some(vec!["source"]), true)]
+    #[case(Some(vec!["quickfix", "source.fixAll"]), true)]
+    #[case(Some(vec!["source.fixAll.journalint"]), true)]
+    #[case(Some(vec!["quickfix"]), false)]
+    #[case(Some(vec!["source.fix"]), false)]
+    #[case(Some(vec!["source.fixAll.journalint.foo"]), false)]
+    fn is_requested_kind(#[case] only: Option<Vec<&'static str>>, #[case] want: bool) {
+        let only: Option<Vec<CodeActionKind>> =
+            only.map(|v| v.into_iter().map(CodeActionKind::new).collect());
+        let kind = CodeActionKind::new(SOURCE_FIX_ALL_JOURNALINT);
+        assert_eq!(super::is_requested_kind(only.as_deref(), &kind), want);
+    }
+
+    const CONTENT: &str = concat!(
+        "---\n",
+        "date: 2006-01-02\n",
+        "start: 09:00\n",
+        "end: 11:00\n",
+        "---\n",
+        "- 09:00-10:00 1.50 😀\n",
+        "- 10:30-11:00 0.50 foo\n",
+    );
+
+    /// Make a server state holding a document, along with its diagnostics.
+    fn open_document(url: &Url, content: &str) -> (ServerState, Vec<lsp_types::Diagnostic>) {
+        let settings = Settings::default();
+        let (journal, diagnostics) = parse_and_lint(url, content, &settings);
+        let line_mapper = Arc::new(LineMapper::with_encoding(content, PositionEncoding::Utf16));
+        let diagnostics = diagnostics
+            .into_iter()
+            .map(|d| d.to_lsptype(&line_mapper))
+            .collect();
+        let mut state = ServerState::new(settings.clone(), PositionEncoding::Utf16);
+        state.set_document_state(
+            url,
+            DocumentState::new(content.to_string(), line_mapper, journal, settings),
+        );
+        (state, diagnostics)
+    }
+
+    #[test]
+    fn quick_fix_code_actions() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (state, diagnostics) = open_document(&url, CONTENT);
+        let diagnostic = diagnostics
+            .iter()
+            .find(|d| d.code == Some(NumberOrString::String("incorrect-duration".into())))
+            .unwrap();
+
+        let actions = super::quick_fix_code_actions(&state, &url, diagnostic).unwrap();
+        assert_eq!(actions.len(), 1);
+        let action = &actions[0];
+        assert_eq!(action.kind, Some(CodeActionKind::QUICKFIX));
+        assert_eq!(action.diagnostics, Some(vec![diagnostic.clone()]));
+        assert_eq!(action.is_preferred, Some(true));
+        let edits = &action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&url];
+        assert_eq!(
+            edits,
+            &vec![lsp_types::TextEdit::new(
+                lsp_types::Range::new(
+                    lsp_types::Position::new(5, 14),
+                    lsp_types::Position::new(5, 18)
+                ),
+                "1.00".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn fix_all_code_action() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let content = CONTENT;
+        let (state, _) = open_document(&url, content);
+
+        let action = super::fix_all_code_action(&state, &url).unwrap().unwrap();
+        let edits = &action.edit.unwrap().changes.unwrap()[&url];
+        assert_eq!(edits.len(), 1);
+        assert_eq!(
+            edits[0].range,
+            lsp_types::Range::new(
+                lsp_types::Position::new(0, 0),
+                lsp_types::Position::new(7, 0)
+            )
+        );
+        assert_eq!(
+            edits[0].new_text,
+            content
+                .replace("1.50", "1.00")
+                .replace("10:30-11:00", "10:00-11:00")
+                .replace("0.50", "1.00")
+        );
+    }
+
+    #[rstest]
+    #[case(None, vec![])]
+    #[case(Some(vec!["quickfix"]), vec![])]
+    #[case(Some(vec!["source.fixAll"]), vec!["source.fixAll.journalint"])]
+    fn code_actions(#[case] only: Option<Vec<&'static str>>, #[case] want: Vec<&str>) {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (state, _) = open_document(&url, CONTENT);
+        let only: Option<Vec<CodeActionKind>> =
+            only.map(|v| v.into_iter().map(CodeActionKind::new).collect());
+
+        // No diagnostics in the context, so no quick fixes
+        let actions = super::code_actions(&state, &url, &[], only.as_deref()).unwrap();
+        let kinds: Vec<&str> = actions
+            .iter()
+            .filter_map(|action| match action {
+                CodeActionOrCommand::CodeAction(action) => action.kind.as_ref(),
+                CodeActionOrCommand::Command(_) => None,
+            })
+            .map(CodeActionKind::as_str)
+            .collect();
+        assert_eq!(kinds, want);
+    }
+
+    #[test]
+    fn hover_at() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (state, _) = open_document(&url, CONTENT);
+
+        let hover = super::hover_at(&state, &url, lsp_types::Position::new(5, 20))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            hover.range,
+            Some(lsp_types::Range::new(
+                lsp_types::Position::new(5, 0),
+                lsp_types::Position::new(5, 21)
+            ))
+        );
+        let HoverContents::Markup(contents) = hover.contents else {
+            panic!("hover contents expected to be markup");
+        };
+        assert!(contents.value.starts_with("**09:00 → 10:00**"));
+
+        let hover = super::hover_at(&state, &url, lsp_types::Position::new(7, 0)).unwrap();
+        assert_eq!(hover, None);
+    }
+
+    #[test]
+    fn completion_items() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let content = format!("{CONTENT}- \n");
+        let (mut state, _) = open_document(&url, &content);
+
+        let items =
+            super::completion_items(&mut state, &url, lsp_types::Position::new(7, 2)).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].text_edit,
+            Some(CompletionTextEdit::Edit(lsp_types::TextEdit::new(
+                lsp_types::Range::new(
+                    lsp_types::Position::new(7, 2),
+                    lsp_types::Position::new(7, 2)
+                ),
+                "11:00-".to_string()
+            )))
+        );
+    }
+
+    #[test]
+    fn inlay_hints_in() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let (state, _) = open_document(&url, CONTENT);
+
+        let range = lsp_types::Range::new(
+            lsp_types::Position::new(5, 0),
+            lsp_types::Position::new(6, 0),
+        );
+        let hints: Vec<(lsp_types::Position, String)> = super::inlay_hints_in(&state, &url, &range)
+            .unwrap()
+            .into_iter()
+            .map(|hint| match hint.label {
+                lsp_types::InlayHintLabel::String(label) => (hint.position, label),
+                _ => panic!("label expected to be a string"),
+            })
+            .collect();
+        assert_eq!(
+            hints,
+            vec![
+                (lsp_types::Position::new(5, 13), "= 1.00h".to_string()),
+                (lsp_types::Position::new(5, 21), "Σ 1.00h".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn formatting_edits() {
+        let url = Url::parse("file:///2006-01-02.md").unwrap();
+        let content = CONTENT
+            .replace("- 09:00", "-  9:00")
+            .replace("0.50 ", "0.5  ");
+        let (state, _) = open_document(&url, &content);
+
+        let edits = super::formatting_edits(&state, &url, None).unwrap();
+        assert_eq!(
+            edits,
+            vec![
+                lsp_types::TextEdit::new(
+                    lsp_types::Range::new(
+                        lsp_types::Position::new(5, 0),
+                        lsp_types::Position::new(5, 21)
+                    ),
+                    "- 09:00-10:00 1.50 😀".to_string()
+                ),
+                lsp_types::TextEdit::new(
+                    lsp_types::Range::new(
+                        lsp_types::Position::new(6, 0),
+                        lsp_types::Position::new(6, 22)
+                    ),
+                    "- 10:30-11:00 0.50 foo".to_string()
+                ),
+            ]
+        );
+
+        let range = lsp_types::Range::new(
+            lsp_types::Position::new(6, 0),
+            lsp_types::Position::new(6, 5),
+        );
+        let edits = super::formatting_edits(&state, &url, Some(&range)).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].new_text, "- 10:30-11:00 0.50 foo");
+    }
+
+    #[rstest]
+    #[case(PositionEncoding::Utf8, 8)]
+    #[case(PositionEncoding::Utf16, 4)]
+    #[case(PositionEncoding::Utf32, 3)]
+    fn apply_content_changes(#[case] encoding: PositionEncoding, #[case] column: u32) {
+        let change =
+            |range: Option<((u32, u32), (u32, u32))>, text: &str| TextDocumentContentChangeEvent {
+                range: range.map(|(start, end)| {
+                    lsp_types::Range::new(
+                        lsp_types::Position::new(start.0, start.1),
+                        lsp_types::Position::new(end.0, end.1),
+                    )
+                }),
+                range_length: None,
+                text: text.to_string(),
+            };
+        let mut content = "- 09:00-10:00 1.00 foo\n".to_string();
+        super::apply_content_changes(
+            &mut content,
+            &[
+                change(None, "x\na亜😀b\n"),
+                change(Some(((1, column), (1, column + 1))), "c"),
+                change(Some(((2, 0), (2, 0))), "d\r\ne"),
+                change(Some(((0, 0), (1, 1))), ""),
+            ],
+            encoding,
+        );
+        assert_eq!(content, "亜😀c\nd\r\ne");
+    }
+}
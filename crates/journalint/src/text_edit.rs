@@ -34,6 +34,43 @@ impl TextEdit {
 
     /// Apply the edit to the given text.
     pub fn apply(&self, content: &mut String) {
-        content.replace_range(self.span().clone(), self.new_text());
+        // The span is in chars as chumsky consumes the input as a char stream, but
+        // `replace_range` takes a range in bytes.
+        let start = byte_offset(content, self.span.start);
+        let end = byte_offset(content, self.span.end);
+        content.replace_range(start..end, self.new_text());
+    }
+}
+
+/// Convert an offset in chars into the one in bytes.
+///
+/// An offset beyond the end of the content is converted into the length of the content.
+fn byte_offset(content: &str, char_offset: usize) -> usize {
+    content
+        .char_indices()
+        .nth(char_offset)
+        .map_or(content.len(), |(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::*;
+
+    #[rstest]
+    #[case("0123456789", 2..4, "abc", "01abc456789")]
+    #[case("作業: 0123", 4..6, "abc", "作業: abc23")]
+    #[case("作業: 0123", 0..2, "休憩", "休憩: 0123")]
+    #[case("作業: 0123", 8..8, "作業", "作業: 0123作業")]
+    fn apply(
+        #[case] content: &str,
+        #[case] span: Range<usize>,
+        #[case] new_text: &str,
+        #[case] want: &str,
+    ) {
+        let mut buffer = content.to_string();
+        TextEdit::new(span, new_text.to_string()).apply(&mut buffer);
+        assert_eq!(buffer, want);
     }
 }